@@ -0,0 +1,93 @@
+//! Benchmarks of the solver on the test datasets, run with `cargo bench --features bench`
+//!
+//! Searches are deterministic, so each benchmark reports its throughput in nodes searched
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, Criterion,
+    Throughput,
+};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use connect4_ai::{bitboard::BitBoard, opening_database::OpeningDatabase, solver::Solver};
+
+/// The number of positions from each dataset solved in every iteration
+const POSITIONS_PER_DATASET: usize = 10;
+
+/// Reads the first positions of a dataset of move strings and scores
+fn load_positions(path: &str) -> Vec<BitBoard> {
+    let file = BufReader::new(File::open(path).expect("failed to open the dataset"));
+    file.lines()
+        .take(POSITIONS_PER_DATASET)
+        .map(|line| {
+            let line = line.expect("failed to read the dataset");
+            let moves = line.split_whitespace().next().expect("invalid test data");
+            BitBoard::from_moves(moves).expect("invalid test data")
+        })
+        .collect()
+}
+
+/// Creates a solver for every position, each with a new transposition table
+fn solvers(boards: &[BitBoard], database: Option<&OpeningDatabase>) -> Vec<Solver> {
+    boards
+        .iter()
+        .map(|&board| {
+            let solver = Solver::new(board);
+            match database {
+                Some(database) => solver.with_opening_database(database.clone()),
+                None => solver,
+            }
+        })
+        .collect()
+}
+
+/// Solves every position, returning the total number of nodes searched
+fn solve_all(solvers: &mut [Solver]) -> u64 {
+    solvers
+        .iter_mut()
+        .map(|solver| solver.solve_with_stats().nodes as u64)
+        .sum()
+}
+
+/// Benchmarks solving the positions, creating the solvers outside of the measurement as
+/// allocating the transposition tables takes longer than solving most endgames
+fn bench_positions(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    boards: &[BitBoard],
+    database: Option<&OpeningDatabase>,
+) {
+    let nodes = solve_all(&mut solvers(boards, database));
+    group.throughput(Throughput::Elements(nodes));
+    group.bench_function(name, |b| {
+        b.iter_batched_ref(
+            || solvers(boards, database),
+            |solvers| solve_all(solvers),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn datasets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solve");
+    group.sample_size(10);
+    for dataset in ["Test_L3_R1", "Test_L2_R1", "Test_L2_R2"].iter() {
+        let boards = load_positions(&format!("test_data/{}", dataset));
+        bench_positions(&mut group, dataset, &boards, None);
+    }
+    group.finish();
+}
+
+fn full_search(c: &mut Criterion) {
+    let database = OpeningDatabase::load().expect("failed to load the opening database");
+    let boards = [BitBoard::new()];
+
+    let mut group = c.benchmark_group("full_search");
+    group.sample_size(10);
+    bench_positions(&mut group, "empty_board", &boards, Some(&database));
+    group.finish();
+}
+
+criterion_group!(benches, datasets, full_search);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Parses arbitrary strings as move strings, run with `cargo fuzz run from_moves`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use connect4_ai::bitboard::BitBoard;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(moves) = std::str::from_utf8(data) {
+        if let Ok(board) = BitBoard::from_moves(moves) {
+            // constructors reject finished games
+            assert!(board.is_consistent());
+            assert!(board.winner().is_none());
+        }
+    }
+});
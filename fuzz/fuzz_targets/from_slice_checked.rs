@@ -0,0 +1,15 @@
+//! Builds boards from arbitrary move slices, run with `cargo fuzz run from_slice_checked`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use connect4_ai::bitboard::BitBoard;
+
+fuzz_target!(|moves: Vec<usize>| {
+    if let Ok(board) = BitBoard::from_slice_checked(&moves) {
+        // constructors reject finished games
+        assert!(board.is_consistent());
+        assert!(board.winner().is_none());
+        assert_eq!(board.num_moves(), moves.len());
+    }
+});
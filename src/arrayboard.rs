@@ -7,6 +7,8 @@ use crossterm::{
 
 use std::io::{stdout, Write};
 
+use connect4_ai::bitboard::GameState;
+
 const HEIGHT: usize = 6;
 const WIDTH: usize = 7;
 
@@ -19,20 +21,10 @@ pub enum Cell {
 
 impl Cell {
     fn is_empty(&self) -> bool {
-        match self {
-            Cell::Empty => true,
-            _ => false,
-        }
+        matches!(self, Cell::Empty)
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub enum GameState {
-    Playing,
-    PlayerOneWin,
-    PlayerTwoWin,
-    Draw,
-}
 #[derive(Clone)]
 pub struct ArrayBoard {
     cells: [Cell; WIDTH * HEIGHT], // cells are stored left-to-right, bottom-to-top
@@ -71,7 +63,7 @@ impl ArrayBoard {
     }
 
     pub fn play_checked(&mut self, column_one_indexed: usize) -> Result<GameState> {
-        if column_one_indexed < 1 || column_one_indexed > WIDTH {
+        if !(1..=WIDTH).contains(&column_one_indexed) {
             return Err(anyhow!(
                 "Invalid move, column {} out of range. Columns must be between 1 and {}",
                 column_one_indexed,
@@ -200,3 +192,50 @@ impl ArrayBoard {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connect4_ai::bitboard::BitBoard;
+
+    #[test]
+    fn win_detection_matches_bitboard() {
+        // a simple linear congruential generator, for reproducible random games
+        let mut state = 0x853c_49e6_748f_ea9bu64;
+        let mut random_column = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % WIDTH
+        };
+
+        for _ in 0..10_000 {
+            let mut array_board = ArrayBoard::new();
+            let mut bitboard = BitBoard::new();
+            loop {
+                // every playable column must agree, not just the one played
+                for column in (0..WIDTH).filter(|&column| array_board.playable(column)) {
+                    assert_eq!(
+                        array_board.check_winning_move(column),
+                        bitboard.check_winning_move(column),
+                        "column {} after {}",
+                        column + 1,
+                        array_board.game
+                    );
+                }
+
+                let column = random_column();
+                if !array_board.playable(column) {
+                    continue;
+                }
+                let state = array_board
+                    .play_checked(column + 1)
+                    .expect("random move is playable");
+                assert_eq!(bitboard.play_checked(column + 1), Ok(state));
+                if state != GameState::Playing {
+                    break;
+                }
+            }
+        }
+    }
+}
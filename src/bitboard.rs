@@ -1,26 +1,84 @@
 //! A compact, computationally efficient bit array representation of a Connect 4 board 
 
+use alloc::{boxed::Box, collections::BTreeSet, format, string::String, vec, vec::Vec};
 use anyhow::{anyhow, Result};
 
-use crate::{HEIGHT, WIDTH};
+use crate::{solver::column_order, threat_map::square_windows_of, HEIGHT, WIDTH};
 
 mod static_masks {
-    use crate::{HEIGHT, WIDTH};
-
-    pub const fn bottom_mask() -> u64 {
+    pub const fn bottom_mask<const W: usize, const H: usize>() -> u64 {
         let mut mask = 0;
         let mut column = 0;
-        while column < WIDTH {
-            mask |= 1 << (column * (HEIGHT + 1));
+        while column < W {
+            mask |= 1 << (column * (H + 1));
             column += 1;
         }
         mask
     }
-    pub const fn full_board_mask() -> u64 {
-        bottom_mask() * ((1 << HEIGHT as u64) - 1)
+    pub const fn full_board_mask<const W: usize, const H: usize>() -> u64 {
+        bottom_mask::<W, H>() * ((1 << H as u64) - 1)
+    }
+}
+
+/// The prefix of codes created by [`BitBoard::to_share_code`]
+///
+/// [`BitBoard::to_share_code`]: struct.GenericBitBoard.html#method.to_share_code
+const SHARE_CODE_PREFIX: &str = "C4-";
+
+/// The digits of share codes, from Crockford's base 32 which excludes easily confused letters
+const SHARE_CODE_ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+
+/// One of the two players of a game, player one moves first
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Player {
+    One,
+    Two,
+}
+
+/// The state of a game, either in progress or finished with its result
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GameState {
+    Playing,
+    PlayerOneWin,
+    PlayerTwoWin,
+    Draw,
+}
+
+/// The reasons a sequence of moves can be rejected when creating or playing on a board
+///
+/// Columns are 0-indexed, but displayed 1-indexed to match move strings
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BoardError {
+    /// The column is outside the board
+    ColumnOutOfRange(usize),
+    /// The column has no empty squares
+    ColumnFull(usize),
+    /// A move was played after the game was won
+    GameAlreadyWon,
+    /// The character is not a column of a move string
+    ParseError(char),
+    /// The row given for a move in the column isn't its lowest empty square
+    RowMismatch(usize),
+}
+
+impl core::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::ColumnOutOfRange(column) => {
+                write!(f, "Invalid move, column {} out of range", column + 1)
+            }
+            Self::ColumnFull(column) => write!(f, "Invalid move, column {} full", column + 1),
+            Self::GameAlreadyWon => write!(f, "Invalid position, game is over"),
+            Self::ParseError(c) => write!(f, "could not parse '{}' as a valid move", c),
+            Self::RowMismatch(column) => {
+                write!(f, "Invalid move, wrong row for column {}", column + 1)
+            }
+        }
     }
 }
 
+impl core::error::Error for BoardError {}
+
 /// A Connect 4 bitboard
 ///
 /// # Notes
@@ -51,32 +109,112 @@ mod static_masks {
 ///
 /// # Internal Representation
 /// This bitboard uses 2 `u64`s for computational efficiency. One `u64` stores a mask of all squares
-/// containing a tile of either color, and the other stores a mask of the current player's tiles
+/// containing a tile of either color, and the other stores a mask of the current player's tiles.
+/// It also caches a mask of the open squares completing an alignment for each player, which
+/// [`BitBoard::play`] updates incrementally for the move ordering and winning move checks
 ///
 /// # Huffman Codes
 /// A board with up to 12 tiles can be encoded into a `u32` using a 
 /// [Huffman code](https://en.wikipedia.org/wiki/Huffman_coding), where the bit sequence `0` separates each 
 /// column and the code sequences `10` and `11` represent the first and second player's tiles respectively.
-/// A board with 12 tiles requires 6 bits of separators and 24 bits of tiles, for 30 bits total
+/// Every column ends with a separator and the code ends with an extra `0`, so a board with 12 tiles
+/// requires 7 bits of separators, 24 bits of tiles and the final bit, for exactly 32 bits total
+///
+/// # Board Sizes
+/// The board is `W` columns wide and `H` rows high. Other sizes than the standard 7x6 [`BitBoard`]
+/// are supported up to 8x7, as long as `W * (H + 1)` bits fit in a `u64`, which is checked at
+/// compile time.
+/// Move strings can only address the first 9 columns
+///
+/// ```
+/// use connect4_ai::bitboard::GenericBitBoard;
+///
+/// assert!(GenericBitBoard::<6, 5>::from_moves("6").is_ok());
+/// assert!(GenericBitBoard::<6, 5>::from_moves("7").is_err());
+/// ```
+/// ```compile_fail
+/// use connect4_ai::bitboard::GenericBitBoard;
+///
+/// // 9 columns of 8 bits don't fit in a u64
+/// let board = GenericBitBoard::<9, 7>::new();
+/// ```
 ///
 /// [transposition table]: ../transposition_table/struct.TranspositionTable.html
 /// [`BitBoard::key`]: #method.key
+/// [`BitBoard::play`]: #method.play
+/// [`BitBoard`]: type.BitBoard.html
 #[derive(Copy, Clone)]
-pub struct BitBoard {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "BitBoardFields", into = "BitBoardFields")
+)]
+pub struct GenericBitBoard<const W: usize, const H: usize> {
     // mask of the current player's tiles
     player_mask: u64,
     // mask of all tiles
     board_mask: u64,
     num_moves: usize,
+    // open squares completing an alignment for the current player and the opponent, updated
+    // incrementally by `play` rather than recalculated at every node of the search
+    player_threats: u64,
+    opponent_threats: u64,
 }
-impl BitBoard {
-    /// Creates a new, empty bitboard
-    pub fn new() -> Self {
+
+/// The serialized form of a bitboard, which is validated when deserializing
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BitBoardFields {
+    player_mask: u64,
+    board_mask: u64,
+    num_moves: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize> From<GenericBitBoard<W, H>> for BitBoardFields {
+    fn from(board: GenericBitBoard<W, H>) -> Self {
         Self {
-            player_mask: 0,
-            board_mask: 0,
-            num_moves: 0,
+            player_mask: board.player_mask,
+            board_mask: board.board_mask,
+            num_moves: board.num_moves,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize> core::convert::TryFrom<BitBoardFields>
+    for GenericBitBoard<W, H>
+{
+    type Error = anyhow::Error;
+
+    fn try_from(fields: BitBoardFields) -> Result<Self> {
+        let board = Self::from_masks(fields.player_mask, fields.board_mask, fields.num_moves)?;
+        // positions where either player has already won are invalid, as in `from_moves`
+        if board.winner().is_some() || Self::has_alignment(board.player_mask) {
+            return Err(anyhow!("Invalid position, game is over"));
         }
+        Ok(board)
+    }
+}
+
+/// A standard 7x6 Connect 4 bitboard (see [`GenericBitBoard`])
+///
+/// [`GenericBitBoard`]: struct.GenericBitBoard.html
+pub type BitBoard = GenericBitBoard<WIDTH, HEIGHT>;
+
+/// The standard 7x6 [`BitBoard`], named to contrast with other board sizes
+///
+/// [`BitBoard`]: type.BitBoard.html
+pub type StandardBoard = BitBoard;
+
+impl<const W: usize, const H: usize> GenericBitBoard<W, H> {
+    // ensure that the board dimensions fit in a u64 for the bitboard representation, a full
+    // column can carry into its extra bit but never any further
+    const DIMENSIONS_FIT: () = assert!(W * (H + 1) <= 64, "board does not fit in a u64");
+
+    /// Creates a new, empty bitboard
+    pub fn new() -> Self {
+        Self::from_parts(0, 0, 0)
     }
 
     /// Creates a board from a string of 1-indexed moves
@@ -85,7 +223,10 @@ impl BitBoard {
     /// The move string is a sequence of columns played, indexed from 1 (meaning `"0"` is an invalid move)
     /// 
     /// Returns `Err` if the move string represents an invalid position. Invalid positions can contain moves
-    /// outside the column range, overfilled columns and winning positions for either player
+    /// outside the column range, overfilled columns and winning positions for either player (see
+    /// [`BoardError`])
+    ///
+    /// [`BoardError`]: enum.BoardError.html
     ///
     /// # Example
     /// ```
@@ -101,45 +242,113 @@ impl BitBoard {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_moves<S: AsRef<str>>(moves: S) -> Result<Self> {
+    pub fn from_moves<S: AsRef<str>>(moves: S) -> Result<Self, BoardError> {
         let mut board = Self::new();
 
         for column_char in moves.as_ref().chars() {
             // only play available moves
             match column_char.to_digit(10).map(|c| c as usize) {
-                Some(column @ 1..=WIDTH) => {
+                Some(column) if (1..=W).contains(&column) => {
                     let column = column - 1;
                     if !board.playable(column) {
-                        return Err(anyhow!("Invalid move, column {} full", column + 1));
+                        return Err(BoardError::ColumnFull(column));
                     }
                     // abort if the position is won at any point
                     if board.check_winning_move(column) {
-                        return Err(anyhow!("Invalid position, game is over"));
+                        return Err(BoardError::GameAlreadyWon);
                     }
-                    let move_bitmap = (board.board_mask + (1 << (column * (HEIGHT + 1))))
-                        & BitBoard::column_mask(column);
-                    board.play(move_bitmap);
+                    board.play_column_unchecked(column);
+                }
+                Some(column) if column > W => return Err(BoardError::ColumnOutOfRange(column - 1)),
+                _ => return Err(BoardError::ParseError(column_char)),
+            }
+        }
+        Ok(board)
+    }
+
+    /// Creates a board from a move list in lettered notation, such as `"1. d1 d2 2. c1 c2"`
+    ///
+    /// # Notes
+    /// Each move is a column letter from `a`, optionally followed by the 1-indexed row the tile
+    /// lands in, and moves are separated by whitespace. Move number tokens like `1.` are ignored
+    ///
+    /// Returns `Err` if the move list represents an invalid position (see
+    /// [`BitBoard::from_moves`]), or [`BoardError::RowMismatch`] if a row doesn't match the
+    /// lowest empty square of its column
+    ///
+    /// [`BitBoard::from_moves`]: #method.from_moves
+    /// [`BoardError::RowMismatch`]: enum.BoardError.html#variant.RowMismatch
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let board = BitBoard::from_notation("1. d1 d2 2. c c2")?;
+    /// assert!(board == BitBoard::from_moves("4433")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_notation(notation: &str) -> Result<Self, BoardError> {
+        let mut board = Self::new();
+
+        for token in notation.split_whitespace() {
+            // skip move numbers
+            let digits = token.trim_end_matches('.');
+            if digits.len() < token.len() && digits.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+
+            let mut chars = token.chars();
+            let column = match chars.next() {
+                Some(letter @ 'a'..='z') => letter as usize - 'a' as usize,
+                Some(c) => return Err(BoardError::ParseError(c)),
+                None => unreachable!("split_whitespace yields non-empty tokens"),
+            };
+            if column >= W {
+                return Err(BoardError::ColumnOutOfRange(column));
+            }
+            if !board.playable(column) {
+                return Err(BoardError::ColumnFull(column));
+            }
+
+            let rank = chars.as_str();
+            if !rank.is_empty() {
+                if let Some(c) = rank.chars().find(|c| !c.is_ascii_digit()) {
+                    return Err(BoardError::ParseError(c));
                 }
-                _ => return Err(anyhow!("could not parse '{}' as a valid move", column_char)),
+                // the tile lands in the row above the highest tile of the column
+                let height = (board.board_mask & Self::column_mask(column)).count_ones() as usize;
+                if rank.parse::<usize>().ok() != Some(height + 1) {
+                    return Err(BoardError::RowMismatch(column));
+                }
+            }
+
+            // abort if the position is won at any point
+            if board.check_winning_move(column) {
+                return Err(BoardError::GameAlreadyWon);
             }
+            board.play_column_unchecked(column);
         }
         Ok(board)
     }
 
     /// Creates a board from a slice of 0-indexed moves
     /// 
-    /// Significantly faster than [`BitBoard::from_moves`] but provides less informative errors
+    /// Significantly faster than [`BitBoard::from_moves`]
     ///
     /// Returns `Err` if the board position is invalid (see [`BitBoard::from_moves`])
     ///
     /// # Warning
     /// This method assumes all items in the slice are in the valid column range, providing numbers too large
-    /// can cause a `panic` in debug builds by bit-shift overflow or produce an unexpected bitboard
+    /// can cause a `panic` in debug builds by bit-shift overflow or produce an unexpected bitboard.
+    /// Use [`BitBoard::from_slice_checked`] for untrusted moves
     /// 
     /// # Example
     /// ```
     /// # use std::error::Error;
-    /// # fn main() -> Result<(), ()> {
+    /// # fn main() -> Result<(), Box<dyn Error>> {
     /// use connect4_ai::bitboard::BitBoard;
     ///
     /// let board = BitBoard::from_slice(&[0, 0, 1, 1, 2, 2])?;
@@ -149,31 +358,424 @@ impl BitBoard {
     /// # }
     /// ```
     /// [`BitBoard::from_moves`]: #method.from_moves
-    pub fn from_slice(moves: &[usize]) -> Result<Self, ()> {
+    /// [`BitBoard::from_slice_checked`]: #method.from_slice_checked
+    pub fn from_slice(moves: &[usize]) -> Result<Self, BoardError> {
+        let mut board = Self::new();
+        for &column in moves.iter() {
+            if !board.playable(column) {
+                return Err(BoardError::ColumnFull(column));
+            }
+            // abort if the position is won at any point
+            if board.check_winning_move(column) {
+                return Err(BoardError::GameAlreadyWon);
+            }
+            board.play_column_unchecked(column);
+        }
+        Ok(board)
+    }
+
+    /// Creates a board from a slice of 0-indexed moves, checking that every column is in range
+    ///
+    /// Unlike [`BitBoard::from_slice`], this is safe to use with untrusted moves. Returns
+    /// [`BoardError::ColumnOutOfRange`] for a column outside the board, or `Err` if the board
+    /// position is otherwise invalid (see [`BitBoard::from_moves`]), for the first invalid move
+    ///
+    /// # Example
+    /// ```
+    /// use connect4_ai::bitboard::{BitBoard, BoardError};
+    ///
+    /// assert!(BitBoard::from_slice_checked(&[3, 3, 2]).is_ok());
+    /// assert_eq!(
+    ///     BitBoard::from_slice_checked(&[3, 7]).err(),
+    ///     Some(BoardError::ColumnOutOfRange(7))
+    /// );
+    /// ```
+    ///
+    /// [`BitBoard::from_slice`]: #method.from_slice
+    /// [`BitBoard::from_moves`]: #method.from_moves
+    /// [`BoardError::ColumnOutOfRange`]: enum.BoardError.html#variant.ColumnOutOfRange
+    pub fn from_slice_checked(moves: &[usize]) -> Result<Self, BoardError> {
         let mut board = Self::new();
         for &column in moves.iter() {
+            // check the range before any masks are shifted by the column
+            if column >= W {
+                return Err(BoardError::ColumnOutOfRange(column));
+            }
             if !board.playable(column) {
-                return Err(());
+                return Err(BoardError::ColumnFull(column));
             }
             // abort if the position is won at any point
             if board.check_winning_move(column) {
-                return Err(());
+                return Err(BoardError::GameAlreadyWon);
             }
-            let move_bitmap =
-                (board.board_mask + (1 << (column * (HEIGHT + 1)))) & BitBoard::column_mask(column);
-            board.play(move_bitmap);
+            board.play_column_unchecked(column);
         }
         Ok(board)
     }
 
+    /// Returns a sequence of 0-indexed moves that reaches the same position from an empty board
+    ///
+    /// # Notes
+    /// Boards don't store the order their moves were played in, so this is one representative
+    /// line with the same tiles in each column, not necessarily the actual history of the game.
+    /// A board that already has a winner can't be recreated with [`BitBoard::from_slice`], which
+    /// rejects winning moves
+    ///
+    /// Returns an empty `Vec` if no sequence of moves reaches the board, which is possible for
+    /// boards created from raw masks
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let board = BitBoard::from_moves("4453")?;
+    /// let moves = board.to_move_sequence();
+    ///
+    /// assert_eq!(BitBoard::from_slice(&moves)?.key(), board.key());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`BitBoard::from_slice`]: #method.from_slice
+    pub fn to_move_sequence(&self) -> Vec<usize> {
+        let mut moves = Vec::with_capacity(self.num_moves);
+        let mut dead_ends = BTreeSet::new();
+        if self.find_move_sequence(Self::new(), &mut moves, &mut dead_ends) {
+            moves
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Depth-first search for moves leading from `partial` to this board, pushing them onto
+    /// `moves`
+    fn find_move_sequence(
+        &self,
+        partial: Self,
+        moves: &mut Vec<usize>,
+        dead_ends: &mut BTreeSet<u64>,
+    ) -> bool {
+        if partial.num_moves == self.num_moves {
+            return true;
+        }
+        // the tiles owned in each square are fixed, so a partial board is identified by its
+        // board mask alone
+        if !dead_ends.insert(partial.board_mask) {
+            return false;
+        }
+
+        // the player to move in the partial board owns these tiles of the final board
+        let mover_tiles = if (self.num_moves - partial.num_moves).is_multiple_of(2) {
+            self.player_mask
+        } else {
+            self.player_mask ^ self.board_mask
+        };
+        let candidates = partial.possible_moves() & mover_tiles;
+        for column in 0..W {
+            let move_bitmap = candidates & Self::column_mask(column);
+            if move_bitmap == 0 {
+                continue;
+            }
+            let mut next = partial;
+            next.play(move_bitmap);
+            moves.push(column);
+            if self.find_move_sequence(next, moves, dead_ends) {
+                return true;
+            }
+            moves.pop();
+        }
+        false
+    }
+
+    /// Creates a board from a text diagram of the grid
+    ///
+    /// # Notes
+    /// The diagram has one line per row, starting with the top row, with `R` for player one's
+    /// tiles, `Y` for player two's tiles and `.` or `_` for empty squares. Whitespace around each
+    /// line and blank lines are ignored. The player to move is derived from the number of tiles
+    ///
+    /// Returns `Err` if the diagram has the wrong dimensions, contains floating tiles, has
+    /// impossible tile counts or either player has already won
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let board = BitBoard::from_diagram(
+    ///     "
+    ///     .......
+    ///     .......
+    ///     .......
+    ///     .......
+    ///     YYY....
+    ///     RRR....
+    ///     ",
+    /// )?;
+    ///
+    /// assert_eq!(board.key(), BitBoard::from_moves("112233")?.key());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_diagram<S: AsRef<str>>(diagram: S) -> Result<Self> {
+        let rows: Vec<&str> = diagram
+            .as_ref()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if rows.len() != H {
+            return Err(anyhow!(
+                "Invalid diagram, expected {} rows but found {}",
+                H,
+                rows.len()
+            ));
+        }
+
+        let mut player_one_mask = 0;
+        let mut player_two_mask = 0;
+        // the first line is the top row
+        for (line, row) in rows.iter().zip((0..H).rev()) {
+            if line.chars().count() != W {
+                return Err(anyhow!(
+                    "Invalid diagram, row '{}' is not {} squares wide",
+                    line,
+                    W
+                ));
+            }
+            for (column, square) in line.chars().enumerate() {
+                let tile = 1 << (column * (H + 1) + row);
+                match square {
+                    'R' => player_one_mask |= tile,
+                    'Y' => player_two_mask |= tile,
+                    '.' | '_' => {}
+                    _ => return Err(anyhow!("could not parse '{}' as a valid square", square)),
+                }
+            }
+        }
+
+        Self::from_player_masks(player_one_mask, player_two_mask)
+    }
+
+    /// Creates a board from masks of each player's tiles, checking the position is possible
+    fn from_player_masks(player_one_mask: u64, player_two_mask: u64) -> Result<Self> {
+        let board_mask = player_one_mask | player_two_mask;
+        if (board_mask + static_masks::bottom_mask::<W, H>()) & board_mask != 0 {
+            return Err(anyhow!("Invalid position, found a floating tile"));
+        }
+        let (player_one_tiles, player_two_tiles) =
+            (player_one_mask.count_ones(), player_two_mask.count_ones());
+        if player_one_tiles != player_two_tiles && player_one_tiles != player_two_tiles + 1 {
+            return Err(anyhow!(
+                "Invalid position, player 1 has {} tiles and player 2 has {}",
+                player_one_tiles,
+                player_two_tiles
+            ));
+        }
+        if Self::has_alignment(player_one_mask) || Self::has_alignment(player_two_mask) {
+            return Err(anyhow!("Invalid position, game is over"));
+        }
+
+        let num_moves = board_mask.count_ones() as usize;
+        // player two moves when player one has an extra tile
+        let player_mask = if num_moves % 2 == 1 {
+            player_two_mask
+        } else {
+            player_one_mask
+        };
+        Ok(Self::from_parts(player_mask, board_mask, num_moves))
+    }
+
+    /// Returns a text diagram of the grid in the format read by [`BitBoard::from_diagram`]
+    ///
+    /// [`BitBoard::from_diagram`]: #method.from_diagram
+    pub fn to_diagram(&self) -> String {
+        let player_one_mask = self.tiles(Player::One);
+
+        let mut diagram = String::with_capacity((W + 1) * H);
+        for row in (0..H).rev() {
+            for column in 0..W {
+                let tile = 1 << (column * (H + 1) + row);
+                diagram.push(if self.board_mask & tile == 0 {
+                    '.'
+                } else if player_one_mask & tile != 0 {
+                    'R'
+                } else {
+                    'Y'
+                });
+            }
+            diagram.push('\n');
+        }
+        diagram
+    }
+
+    /// Creates a board from a string of every square's contents
+    ///
+    /// # Notes
+    /// The string has one character per square, starting from the bottom row, with each row
+    /// read from left to right. `0` is an empty square, and `1` and `2` are player one's and
+    /// player two's tiles. The player to move is derived from the number of tiles
+    ///
+    /// Returns `Err` if the string has the wrong length, contains floating tiles, has impossible
+    /// tile counts or either player has already won
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let board = BitBoard::from_cells_string(
+    ///     "111000022200000000000000000000000000000000",
+    /// )?;
+    ///
+    /// assert_eq!(board.key(), BitBoard::from_moves("112233")?.key());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_cells_string<S: AsRef<str>>(cells: S) -> Result<Self> {
+        let cells = cells.as_ref();
+        if cells.chars().count() != W * H {
+            return Err(anyhow!(
+                "Invalid cells string, expected {} squares but found {}",
+                W * H,
+                cells.chars().count()
+            ));
+        }
+
+        let mut player_one_mask = 0;
+        let mut player_two_mask = 0;
+        for (i, square) in cells.chars().enumerate() {
+            let (row, column) = (i / W, i % W);
+            let tile = 1 << (column * (H + 1) + row);
+            match square {
+                '1' => player_one_mask |= tile,
+                '2' => player_two_mask |= tile,
+                '0' => {}
+                _ => return Err(anyhow!("could not parse '{}' as a valid square", square)),
+            }
+        }
+        Self::from_player_masks(player_one_mask, player_two_mask)
+    }
+
+    /// Returns a string of every square's contents in the format read by
+    /// [`BitBoard::from_cells_string`]
+    ///
+    /// [`BitBoard::from_cells_string`]: #method.from_cells_string
+    pub fn to_cells_string(&self) -> String {
+        let player_one_mask = self.tiles(Player::One);
+
+        let mut cells = String::with_capacity(W * H);
+        for row in 0..H {
+            for column in 0..W {
+                let tile = 1 << (column * (H + 1) + row);
+                cells.push(if self.board_mask & tile == 0 {
+                    '0'
+                } else if player_one_mask & tile != 0 {
+                    '1'
+                } else {
+                    '2'
+                });
+            }
+        }
+        cells
+    }
+
     /// Creates a bitboard from its constituent bit masks and move counter (see [Internal Representation])
     /// [Internal Representation]: #internal-representation
     pub fn from_parts(player_mask: u64, board_mask: u64, num_moves: usize) -> Self {
-        Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::DIMENSIONS_FIT;
+        let mut board = Self {
             player_mask,
             board_mask,
             num_moves,
+            player_threats: 0,
+            opponent_threats: 0,
+        };
+        board.update_threats();
+        board
+    }
+
+    /// Recalculates both players' threat masks from scratch
+    fn update_threats(&mut self) {
+        self.player_threats = self.winning_positions(self.player_mask);
+        self.opponent_threats = self.winning_positions(self.player_mask ^ self.board_mask);
+    }
+
+    /// Creates a bitboard from its constituent bit masks and move counter, checking that they
+    /// are consistent (see [`BitBoard::is_consistent`])
+    ///
+    /// Returns `Err` if the current player has tiles outside the board mask, the move counter
+    /// doesn't match the number of tiles or the board is otherwise inconsistent. Use
+    /// [`BitBoard::from_parts`] to skip the checks for masks that are known to be valid
+    ///
+    /// [`BitBoard::is_consistent`]: #method.is_consistent
+    /// [`BitBoard::from_parts`]: #method.from_parts
+    pub fn from_masks(player_mask: u64, board_mask: u64, num_moves: usize) -> Result<Self> {
+        if player_mask & !board_mask != 0 {
+            return Err(anyhow!(
+                "Invalid masks, player mask has tiles outside the board mask"
+            ));
+        }
+        if board_mask.count_ones() as usize != num_moves {
+            return Err(anyhow!(
+                "Invalid masks, board mask has {} tiles but {} moves were played",
+                board_mask.count_ones(),
+                num_moves
+            ));
+        }
+        let board = Self::from_parts(player_mask, board_mask, num_moves);
+        if !board.is_consistent() {
+            return Err(anyhow!("Invalid masks, board is inconsistent"));
+        }
+        Ok(board)
+    }
+
+    /// Returns whether the internal masks and move counter describe a possible board
+    ///
+    /// # Notes
+    /// A consistent board has no tiles outside the board or floating above empty squares,
+    /// no current player tiles outside the board mask, a move counter matching the number of
+    /// tiles and the correct number of tiles for the current player. It does not check whether
+    /// the game is already over
+    pub fn is_consistent(&self) -> bool {
+        // every column is a contiguous run of tiles from the bottom, so adding the bottom
+        // square carries through each run without touching any other tiles
+        let gravity =
+            (self.board_mask + static_masks::bottom_mask::<W, H>()) & self.board_mask == 0;
+
+        self.board_mask & !static_masks::full_board_mask::<W, H>() == 0
+            && self.player_mask & !self.board_mask == 0
+            && gravity
+            && self.board_mask.count_ones() as usize == self.num_moves
+            && self.player_mask.count_ones() as usize == self.num_moves / 2
+    }
+
+    /// Returns whether the board could be reached by legal play from an empty board
+    ///
+    /// # Notes
+    /// In addition to [`is_consistent`](Self::is_consistent), this checks that the game did not
+    /// end before the last move: the current player has no alignment, and every alignment of the
+    /// other player passes through a single tile they could have played last
+    pub fn is_legal_position(&self) -> bool {
+        if !self.is_consistent() || Self::has_alignment(self.player_mask) {
+            return false;
+        }
+
+        let opponent_mask = self.player_mask ^ self.board_mask;
+        if !Self::has_alignment(opponent_mask) {
+            return true;
         }
+        // the winning move must be the top tile of some column
+        let top_tiles = self.board_mask & !(self.board_mask >> 1) & opponent_mask;
+        (0..W).any(|column| {
+            let tile = top_tiles & Self::column_mask(column);
+            tile != 0 && !Self::has_alignment(opponent_mask & !tile)
+        })
     }
 
     /// Accesses the internal mask of the current player's tiles
@@ -188,37 +790,35 @@ impl BitBoard {
 
     /// Returns a mask of the top square of a given column
     pub fn top_mask(column: usize) -> u64 {
-        1 << (column * (HEIGHT + 1) + (HEIGHT - 1))
+        1 << (column * (H + 1) + (H - 1))
     }
 
     /// Returns a mask of the bottom square of a given column
     pub fn bottom_mask(column: usize) -> u64 {
-        1 << (column * (HEIGHT + 1))
+        1 << (column * (H + 1))
     }
 
     /// Returns a mask of the given column
     pub fn column_mask(column: usize) -> u64 {
-        ((1 << HEIGHT) - 1) << (column * (HEIGHT + 1))
+        ((1 << H) - 1) << (column * (H + 1))
     }
 
-    /// Returns the column represented by a move bitmap or [`WIDTH`] if the column is not found
-    ///
-    /// [`WIDTH`]: ../constant.WIDTH.html
+    /// Returns the column represented by a move bitmap or `W` if the column is not found
     pub fn column_from_move(move_bitmap: u64) -> usize {
-        for column in 0..WIDTH {
+        for column in 0..W {
             if move_bitmap & Self::column_mask(column) != 0 {
                 return column;
             }
         }
-        // WIDTH is always an invalid column
-        WIDTH
+        // W is always an invalid column
+        W
     }
 
     /// Returns a bitmap of all moves that don't give the opponent an immediate win
     pub fn non_losing_moves(&self) -> u64 {
         let mut possible_moves = self.possible_moves();
-        let opponent_winning_positions = self.opponent_winning_positions();
-        let forced_moves = possible_moves & opponent_winning_positions;
+        let opponent_threats = self.opponent_threats;
+        let forced_moves = possible_moves & opponent_threats;
 
         if forced_moves != 0 {
             // if more than one forced move exists, you can't prevent the opponent winning
@@ -229,66 +829,171 @@ impl BitBoard {
             }
         }
         // avoid playing below an opponent's winning move
-        possible_moves & !(opponent_winning_positions >> 1)
+        possible_moves & !(opponent_threats >> 1)
+    }
+
+    /// Returns whether the side to move is already lost
+    ///
+    /// # Notes
+    /// A position is lost when the current player cannot win on this move and every legal
+    /// move gives the opponent an immediate win, for example against a double threat
+    pub fn is_lost(&self) -> bool {
+        !self.is_full()
+            && self.non_losing_moves() == 0
+            && self.player_threats & self.possible_moves() == 0
+    }
+
+    /// Returns whether every square on the board is filled
+    pub fn is_full(&self) -> bool {
+        self.num_moves == W * H
     }
 
     /// Returns a mask of all possible moves in the position
     pub fn possible_moves(&self) -> u64 {
-        (self.board_mask + static_masks::bottom_mask()) & static_masks::full_board_mask()
+        (self.board_mask + static_masks::bottom_mask::<W, H>())
+            & static_masks::full_board_mask::<W, H>()
     }
 
-    /// Returns a bitmap of open squares that complete alignments for the opponent
-    fn opponent_winning_positions(&self) -> u64 {
-        let opp_mask = self.player_mask ^ self.board_mask;
-        self.winning_positions(opp_mask)
+    /// Returns an iterator over the playable columns (0-indexed), ordered from the middle
+    /// outwards like [`move_order`]
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// // the middle column is full
+    /// let board = BitBoard::from_moves("444444")?;
+    ///
+    /// assert_eq!(board.legal_moves().collect::<Vec<_>>(), vec![4, 2, 5, 1, 6, 0]);
+    /// assert_eq!(board.legal_move_count(), 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`move_order`]: ../solver/fn.move_order.html
+    pub fn legal_moves(&self) -> impl Iterator<Item = usize> {
+        let possible_moves = self.possible_moves();
+        IntoIterator::into_iter(column_order::<W>())
+            .filter(move |&column| possible_moves & Self::column_mask(column) != 0)
     }
 
-    /// Returns a mask of open squares of the current player's partial alignments
-    fn winning_positions(&self, player_mask: u64) -> u64 {
-        // vertical
-        // find the top ends of 3-alignemnts
-        let mut r = (player_mask << 1) & (player_mask << 2) & (player_mask << 3);
-
-        // horizontal
-        let mut p = (player_mask << (HEIGHT + 1)) & (player_mask << (2 * (HEIGHT + 1)));
-        // find the right ends of 3-alignments
-        r |= p & (player_mask << (3 * (HEIGHT + 1)));
-        // find holes of the type ...O O _ O...
-        r |= p & (player_mask >> (HEIGHT + 1));
+    /// Returns the number of playable columns
+    pub fn legal_move_count(&self) -> usize {
+        self.possible_moves().count_ones() as usize
+    }
 
-        p = (player_mask >> (HEIGHT + 1)) & (player_mask >> (2 * (HEIGHT + 1)));
-        // find the left ends of 3-alignments
-        r |= p & (player_mask >> (3 * (HEIGHT + 1)));
-        // find holes of the type ...O _ O O...
-        r |= p & (player_mask << (HEIGHT + 1));
+    /// Returns a mask of the playable columns, with bit `c` set if the 0-indexed column `c` is
+    /// playable
+    ///
+    /// Only the first 8 columns fit in the mask, so wider boards should use
+    /// [`legal_moves`](#method.legal_moves) instead
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// // the middle column is full
+    /// let board = BitBoard::from_moves("444444")?;
+    ///
+    /// assert_eq!(board.playable_mask(), 0b111_0111);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn playable_mask(&self) -> u8 {
+        let possible_moves = self.possible_moves();
+        (0..W.min(8))
+            .filter(|&column| possible_moves & Self::column_mask(column) != 0)
+            .fold(0, |mask, column| mask | 1 << column)
+    }
+
+    /// Returns a mask of the empty squares that would complete an alignment of 4 for the current
+    /// player
+    ///
+    /// # Notes
+    /// Squares use the same bit indices as the board masks, with bit `column * (H + 1) + row`
+    /// for row 0 at the bottom (see [Notes](#notes) for the 7x6 layout). Threats can be anywhere
+    /// above the top tile of a column, not just in the next playable square
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let board = BitBoard::from_moves("112233")?;
+    ///
+    /// // the bottom square of column 3
+    /// assert_eq!(board.threats(), 1 << (3 * 7));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn threats(&self) -> u64 {
+        self.player_threats
+    }
+
+    /// Returns a mask of the empty squares that would complete an alignment of 4 for the
+    /// opponent, with the same layout as [`threats`](#method.threats)
+    pub fn opponent_threats(&self) -> u64 {
+        self.opponent_threats
+    }
+
+    /// Returns an iterator over the columns (0-indexed) containing at least one of the current
+    /// player's [`threats`](#method.threats), in ascending order
+    pub fn threat_columns(&self) -> impl Iterator<Item = usize> {
+        let threats = self.player_threats;
+        (0..W).filter(move |&column| threats & Self::column_mask(column) != 0)
+    }
+
+    /// Returns a mask of open squares of the current player's partial alignments
+    pub(crate) fn winning_positions(&self, player_mask: u64) -> u64 {
+        // vertical
+        // find the top ends of 3-alignemnts
+        let mut r = (player_mask << 1) & (player_mask << 2) & (player_mask << 3);
+
+        // horizontal
+        let mut p = (player_mask << (H + 1)) & (player_mask << (2 * (H + 1)));
+        // find the right ends of 3-alignments
+        r |= p & (player_mask << (3 * (H + 1)));
+        // find holes of the type ...O O _ O...
+        r |= p & (player_mask >> (H + 1));
+
+        p = (player_mask >> (H + 1)) & (player_mask >> (2 * (H + 1)));
+        // find the left ends of 3-alignments
+        r |= p & (player_mask >> (3 * (H + 1)));
+        // find holes of the type ...O _ O O...
+        r |= p & (player_mask << (H + 1));
 
         // diagonal /
-        p = (player_mask << HEIGHT) & (player_mask << (2 * HEIGHT));
+        p = (player_mask << H) & (player_mask << (2 * H));
         // find the right ends of 3-alignments
-        r |= p & (player_mask << (3 * (HEIGHT)));
+        r |= p & (player_mask << (3 * (H)));
         // find holes of the type ...O O _ O...
-        r |= p & (player_mask >> (HEIGHT));
+        r |= p & (player_mask >> (H));
 
-        p = (player_mask >> (HEIGHT)) & (player_mask >> (2 * HEIGHT));
+        p = (player_mask >> (H)) & (player_mask >> (2 * H));
         // find the left ends of 3-alignments
-        r |= p & (player_mask >> (3 * (HEIGHT)));
+        r |= p & (player_mask >> (3 * (H)));
         // find holes of the type ...O _ O O...
-        r |= p & (player_mask << (HEIGHT));
+        r |= p & (player_mask << (H));
 
         // diagonal \
-        p = (player_mask << (HEIGHT + 2)) & (player_mask << (2 * (HEIGHT + 2)));
+        p = (player_mask << (H + 2)) & (player_mask << (2 * (H + 2)));
         // find the right ends of 3-alignments
-        r |= p & (player_mask << (3 * (HEIGHT + 2)));
+        r |= p & (player_mask << (3 * (H + 2)));
         // find holes of the type ...O O _ O...
-        r |= p & (player_mask >> (HEIGHT + 2));
+        r |= p & (player_mask >> (H + 2));
 
-        p = (player_mask >> (HEIGHT + 2)) & (player_mask >> (2 * (HEIGHT + 2)));
+        p = (player_mask >> (H + 2)) & (player_mask >> (2 * (H + 2)));
         // find the left ends of 3-alignments
-        r |= p & (player_mask >> (3 * (HEIGHT + 2)));
+        r |= p & (player_mask >> (3 * (H + 2)));
         // find holes of the type ...O _ O O...
-        r |= p & (player_mask << (HEIGHT + 2));
+        r |= p & (player_mask << (H + 2));
 
-        r & (static_masks::full_board_mask() ^ self.board_mask)
+        r & (static_masks::full_board_mask::<W, H>() ^ self.board_mask)
     }
 
     /// Scores a move bitmap by counting open 3-alignments after the move
@@ -303,6 +1008,20 @@ impl BitBoard {
         self.num_moves
     }
 
+    /// Returns whether player one is to move, which is after an even number of moves
+    pub fn is_first_player_turn(&self) -> bool {
+        self.num_moves.is_multiple_of(2)
+    }
+
+    /// Returns the player to move
+    pub fn current_player(&self) -> Player {
+        if self.is_first_player_turn() {
+            Player::One
+        } else {
+            Player::Two
+        }
+    }
+
     /// Returns whether a column is a legal move
     pub fn playable(&self, column: usize) -> bool {
         Self::top_mask(column) & self.board_mask == 0
@@ -310,53 +1029,311 @@ impl BitBoard {
 
     /// Advances the game by applying a move bitmap and switching players
     pub fn play(&mut self, move_bitmap: u64) {
+        let mover_mask = self.player_mask | move_bitmap;
         // switch the current player
         self.player_mask ^= self.board_mask;
         // add a cell of the previous player to the correct column
         self.board_mask |= move_bitmap;
         self.num_moves += 1;
+
+        // the new current player only loses the filled square, while the tile just played can
+        // give its owner new threats
+        let player_threats = self.opponent_threats & !move_bitmap;
+        self.opponent_threats = self.winning_positions(mover_mask);
+        self.player_threats = player_threats;
+    }
+
+    /// Plays a tile in a 0-indexed column and switches players
+    ///
+    /// Returns [`BoardError::ColumnOutOfRange`] or [`BoardError::ColumnFull`] if the column can't
+    /// be played
+    ///
+    /// [`BoardError::ColumnOutOfRange`]: enum.BoardError.html#variant.ColumnOutOfRange
+    /// [`BoardError::ColumnFull`]: enum.BoardError.html#variant.ColumnFull
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let mut board = BitBoard::from_moves("444444")?;
+    /// board.play_column(0)?;
+    ///
+    /// assert!(board.play_column(3).is_err());
+    /// assert!(board.play_column(7).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn play_column(&mut self, column: usize) -> Result<(), BoardError> {
+        if column >= W {
+            return Err(BoardError::ColumnOutOfRange(column));
+        }
+        if !self.playable(column) {
+            return Err(BoardError::ColumnFull(column));
+        }
+        self.play_column_unchecked(column);
+        Ok(())
+    }
+
+    /// Plays a tile in a 1-indexed column, as in move strings, returning the state of the game
+    /// after the move
+    ///
+    /// Returns [`BoardError::GameAlreadyWon`] if either player has already won,
+    /// [`BoardError::ColumnOutOfRange`] or [`BoardError::ColumnFull`] if the column can't be
+    /// played, and [`BoardError::ParseError`] for column 0, like the `'0'` character in a move
+    /// string
+    ///
+    /// [`BoardError::GameAlreadyWon`]: enum.BoardError.html#variant.GameAlreadyWon
+    /// [`BoardError::ColumnOutOfRange`]: enum.BoardError.html#variant.ColumnOutOfRange
+    /// [`BoardError::ColumnFull`]: enum.BoardError.html#variant.ColumnFull
+    /// [`BoardError::ParseError`]: enum.BoardError.html#variant.ParseError
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::{BitBoard, GameState};
+    ///
+    /// let mut board = BitBoard::from_moves("112233")?;
+    ///
+    /// assert_eq!(board.play_checked(4)?, GameState::PlayerOneWin);
+    /// assert!(board.play_checked(5).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn play_checked(&mut self, column_one_indexed: usize) -> Result<GameState, BoardError> {
+        if self.has_winner() {
+            return Err(BoardError::GameAlreadyWon);
+        }
+        if column_one_indexed == 0 {
+            return Err(BoardError::ParseError('0'));
+        }
+        self.play_column(column_one_indexed - 1)?;
+        Ok(self.game_state())
+    }
+
+    /// Plays a tile in a 0-indexed column and switches players, without checking the column
+    ///
+    /// # Warning
+    /// The column must be in range and playable, otherwise this can `panic` in debug builds by
+    /// bit-shift overflow or produce an unexpected bitboard
+    pub fn play_column_unchecked(&mut self, column: usize) {
+        debug_assert!(column < W && self.playable(column));
+        let move_bitmap = (self.board_mask + Self::bottom_mask(column)) & Self::column_mask(column);
+        self.play(move_bitmap);
+    }
+
+    /// Reverts [`play`] for the last move played, given the same move bitmap, switching back to
+    /// the previous player
+    ///
+    /// # Notes
+    /// The board doesn't keep a history of its moves, so the caller must keep the move bitmaps
+    /// to undo them in reverse order. Undoing a bitmap that isn't the topmost tile of its column
+    /// leaves the board in an inconsistent state
+    ///
+    /// [`play`]: #method.play
+    pub fn undo(&mut self, move_bitmap: u64) {
+        debug_assert!(self.num_moves > 0 && move_bitmap & !self.board_mask == 0);
+        self.board_mask ^= move_bitmap;
+        // the previous player's tiles are the opponent's tiles before the move
+        self.player_mask ^= self.board_mask;
+        self.num_moves -= 1;
+        self.update_threats();
+    }
+
+    /// Returns a mask of the cells occupied in exactly one of the two boards
+    pub fn diff(&self, other: &Self) -> u64 {
+        self.board_mask ^ other.board_mask
+    }
+
+    /// Returns whether this board is `parent` after exactly one legal move
+    pub fn is_child_of(&self, parent: &Self) -> bool {
+        let move_bitmap = self.diff(parent);
+        // the game must not have been over before the move
+        if move_bitmap.count_ones() != 1
+            || move_bitmap & parent.possible_moves() == 0
+            || parent.winner().is_some()
+        {
+            return false;
+        }
+        let mut child = *parent;
+        child.play(move_bitmap);
+
+        child.player_mask == self.player_mask && child.num_moves == self.num_moves
     }
 
     /// Returns whether a column is a winning move
     pub fn check_winning_move(&self, column: usize) -> bool {
-        let mut pos = self.player_mask;
-        // play the move on the clone of the board, keeping the current player
-        pos |= (self.board_mask + Self::bottom_mask(column)) & Self::column_mask(column);
+        self.player_threats & self.possible_moves() & Self::column_mask(column) != 0
+    }
+
+    /// Returns whether a player's tile mask contains an alignment in any direction
+    fn has_alignment(player_mask: u64) -> bool {
+        Self::has_horizontal_win(player_mask)
+            || Self::has_diagonal_win(player_mask)
+            || Self::has_vertical_win(player_mask)
+    }
+
+    /// Returns the player who won the game with the last move played, or `None` if the game is
+    /// still in progress or drawn
+    ///
+    /// # Notes
+    /// Playing a move switches the current player, so the winner is always the player who is
+    /// *not* to move in the final position
+    pub fn winner(&self) -> Option<Player> {
+        if Self::has_alignment(self.player_mask ^ self.board_mask) {
+            // player one plays the odd-numbered moves
+            if self.num_moves % 2 == 1 {
+                Some(Player::One)
+            } else {
+                Some(Player::Two)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mask of four aligned squares of the player who won with the last move played,
+    /// or `None` if the game hasn't been won
+    ///
+    /// # Notes
+    /// If the winning move completed several alignments, or a line of more than four tiles, one
+    /// set of four squares is chosen. This is useful for highlighting the winning tiles at the
+    /// end of a game
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::BitBoard;
+    ///
+    /// let mut board = BitBoard::from_moves("112233")?;
+    /// assert_eq!(board.winning_line(), None);
+    ///
+    /// board.play_column(3)?;
+    /// // the bottom squares of the first 4 columns
+    /// assert_eq!(board.winning_line(), Some(0x204081));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn winning_line(&self) -> Option<u64> {
+        let mover_mask = self.player_mask ^ self.board_mask;
+
+        // vertical, horizontal and both diagonal directions as bit shifts
+        for &shift in [1, H + 1, H, H + 2].iter() {
+            // mark the first square of every run of 4 in this direction
+            let pairs = mover_mask & (mover_mask >> shift);
+            let runs = pairs & (pairs >> (2 * shift));
+            if runs != 0 {
+                let first = runs & runs.wrapping_neg();
+                return Some(first | first << shift | first << (2 * shift) | first << (3 * shift));
+            }
+        }
+        None
+    }
+
+    /// Returns whether the last move played completed an alignment
+    pub fn has_winner(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    /// Returns whether the game is in progress, won by either player or drawn
+    ///
+    /// # Notes
+    /// A full board is only a draw if the last move didn't win
+    pub fn game_state(&self) -> GameState {
+        match self.winner() {
+            Some(Player::One) => GameState::PlayerOneWin,
+            Some(Player::Two) => GameState::PlayerTwoWin,
+            None if self.is_full() => GameState::Draw,
+            None => GameState::Playing,
+        }
+    }
 
-        // check horizontal alignment
+    /// Returns whether a player's tile mask contains a horizontal alignment
+    pub fn has_horizontal_win(player_mask: u64) -> bool {
         // mark all horizontal runs of 2
-        let mut m = pos & (pos >> (HEIGHT + 1));
+        let m = player_mask & (player_mask >> (H + 1));
         // check for runs of 2 * (runs of 2)
-        if m & (m >> (2 * (HEIGHT + 1))) != 0 {
-            return true;
-        }
+        m & (m >> (2 * (H + 1))) != 0
+    }
 
+    /// Returns whether a player's tile mask contains a diagonal alignment in either direction
+    pub fn has_diagonal_win(player_mask: u64) -> bool {
         // check diagonal alignment 1
         // mark all diagonal runs of 2
-        m = pos & (pos >> HEIGHT);
+        let mut m = player_mask & (player_mask >> H);
         // check for runs of 2 * (runs of 2)
-        if m & (m >> (2 * HEIGHT)) != 0 {
+        if m & (m >> (2 * H)) != 0 {
             return true;
         }
 
         // check diagonal alignment 2
-        // mark all horizontal runs of 2
-        m = pos & (pos >> (HEIGHT + 2));
+        // mark all diagonal runs of 2
+        m = player_mask & (player_mask >> (H + 2));
         // check for runs of 2 * (runs of 2)
-        if m & (m >> (2 * (HEIGHT + 2))) != 0 {
-            return true;
-        }
+        m & (m >> (2 * (H + 2))) != 0
+    }
 
-        // check vertical alignment
+    /// Returns whether a player's tile mask contains a vertical alignment
+    pub fn has_vertical_win(player_mask: u64) -> bool {
         // mark all vertical runs of 2
-        m = pos & (pos >> 1);
+        let m = player_mask & (player_mask >> 1);
         // check for runs of 2 * (runs of 2)
-        if m & (m >> 2) != 0 {
-            return true;
+        m & (m >> 2) != 0
+    }
+
+    /// Returns a mask of a player's tiles, regardless of whose turn it is
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::bitboard::{BitBoard, Player};
+    ///
+    /// let board = BitBoard::from_moves("445")?;
+    ///
+    /// assert_eq!(board.tiles(Player::One).count_ones(), 2);
+    /// assert_eq!(board.tiles(Player::Two).count_ones(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tiles(&self, player: Player) -> u64 {
+        if self.current_player() == player {
+            self.player_mask
+        } else {
+            self.player_mask ^ self.board_mask
         }
+    }
 
-        // no alignments
-        false
+    /// Counts the four-in-a-row windows of the board that contain none of the opponent's tiles,
+    /// and so could still be completed by `player`
+    pub fn open_lines(&self, player: Player) -> u32 {
+        let opponent_mask = match player {
+            Player::One => self.tiles(Player::Two),
+            Player::Two => self.tiles(Player::One),
+        };
+        let mut count = 0;
+
+        // horizontal, vertical and both diagonal directions as (column, row) steps
+        for &(dc, dr) in [(1, 0), (0, 1), (1, 1), (1, -1)].iter() {
+            for column in 0..W as i32 {
+                for row in 0..H as i32 {
+                    let (end_column, end_row) = (column + 3 * dc, row + 3 * dr);
+                    if end_column >= W as i32 || !(0..H as i32).contains(&end_row) {
+                        continue;
+                    }
+                    let window = (0..4).fold(0u64, |window, i| {
+                        window | 1 << ((column + i * dc) * (H as i32 + 1) + row + i * dr)
+                    });
+                    if window & opponent_mask == 0 {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
     }
 
     /// Returns the key used for indexing into the transposition table (see [Board Keys])
@@ -366,15 +1343,183 @@ impl BitBoard {
         self.player_mask + self.board_mask
     }
 
+    /// Returns a 64-bit identifier of the position that is different for every consistent
+    /// board, for keying external caches
+    ///
+    /// # Notes
+    /// This is the full board key (see [Board Keys]). Each column of the key holds the current
+    /// player's tiles plus a run of 1-bits as high as the column, which sums to a value between
+    /// `2^height - 1` and `2^(height + 1) - 2`, so the column's height and tiles can both be read
+    /// back and no column carries into the next. The [transposition table] only keeps part of
+    /// each key and can confuse positions, but this identifier is never shared, and is stable
+    /// between versions as it only depends on the tiles on the board
+    ///
+    /// [Board Keys]: #board-keys
+    /// [transposition table]: ../transposition_table/struct.TranspositionTable.html
+    pub fn unique_id(&self) -> u64 {
+        self.key()
+    }
+
+    /// Returns a short code identifying the position, for sharing positions in bug reports
+    ///
+    /// # Notes
+    /// The code is the board key (see [Board Keys]) in lowercase
+    /// [Crockford base 32](https://www.crockford.com/base32.html) with a `C4-` prefix, and is read
+    /// back with [`BitBoard::from_share_code`]
+    ///
+    /// [Board Keys]: #board-keys
+    /// [`BitBoard::from_share_code`]: #method.from_share_code
+    pub fn to_share_code(&self) -> String {
+        let mut key = self.key();
+        let mut digits = Vec::new();
+        loop {
+            digits.push(SHARE_CODE_ALPHABET[(key % 32) as usize]);
+            key /= 32;
+            if key == 0 {
+                break;
+            }
+        }
+        digits.reverse();
+        format!("{}{}", SHARE_CODE_PREFIX, String::from_utf8_lossy(&digits))
+    }
+
+    /// Creates a bitboard from a code created by [`BitBoard::to_share_code`]
+    ///
+    /// Returns `Err` if the code is malformed or represents an invalid position. Codes are not
+    /// case sensitive
+    ///
+    /// [`BitBoard::to_share_code`]: #method.to_share_code
+    pub fn from_share_code<S: AsRef<str>>(code: S) -> Result<Self> {
+        let code = code.as_ref();
+        let digits = code
+            .get(..SHARE_CODE_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(SHARE_CODE_PREFIX))
+            .map(|_| &code[SHARE_CODE_PREFIX.len()..])
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid share code, expected '{}' prefix",
+                    SHARE_CODE_PREFIX
+                )
+            })?;
+        if digits.is_empty() {
+            return Err(anyhow!("Invalid share code, no digits found"));
+        }
+
+        let mut key: u64 = 0;
+        for digit in digits.bytes() {
+            let value = SHARE_CODE_ALPHABET
+                .iter()
+                .position(|&c| c == digit.to_ascii_lowercase())
+                .ok_or_else(|| anyhow!("Invalid share code, '{}' is not a digit", digit as char))?;
+            key = key
+                .checked_mul(32)
+                .map(|key| key + value as u64)
+                .filter(|&key| key.checked_shr((W * (H + 1)) as u32).unwrap_or(0) == 0)
+                .ok_or_else(|| anyhow!("Invalid share code, key is too large"))?;
+        }
+
+        // each column of the key is the current player's tiles plus a mask of the column's
+        // tiles, which is between 2^n - 1 and 2^(n+1) - 2 for a column with n tiles
+        let (mut player_mask, mut board_mask) = (0, 0);
+        for column in 0..W {
+            let shift = column * (H + 1);
+            let column_key = (key >> shift) & ((1 << (H + 1)) - 1);
+            let height = 63 - (column_key + 1).leading_zeros() as usize;
+            if height > H {
+                return Err(anyhow!(
+                    "Invalid share code, column {} is overfull",
+                    column + 1
+                ));
+            }
+            let column_mask = (1 << height) - 1;
+            board_mask |= column_mask << shift;
+            player_mask |= (column_key - column_mask) << shift;
+        }
+
+        let num_moves = board_mask.count_ones() as usize;
+        let opponent_mask = player_mask ^ board_mask;
+        // player two is to move after an odd number of moves
+        if num_moves % 2 == 1 {
+            Self::from_player_masks(opponent_mask, player_mask)
+        } else {
+            Self::from_player_masks(player_mask, opponent_mask)
+        }
+    }
+
+    /// Returns whether the board is the same as its mirror image
+    ///
+    /// # Notes
+    /// Mirrored moves in a symmetric position lead to mirrored positions with the same score, so
+    /// only one of each pair needs to be searched (see [`Solver::prune_symmetric_moves`])
+    ///
+    /// [`Solver::prune_symmetric_moves`]: ../solver/struct.GenericSolver.html#method.prune_symmetric_moves
+    pub fn is_symmetric(&self) -> bool {
+        Self::mirror_mask(self.board_mask) == self.board_mask
+            && Self::mirror_mask(self.player_mask) == self.player_mask
+    }
+
+    /// Returns the mirror image of the board, with column `c` moved to column `W - 1 - c`
+    ///
+    /// The mirror image has the same number of moves and player to move, and the same score
+    pub fn mirror(&self) -> Self {
+        Self::from_parts(
+            Self::mirror_mask(self.player_mask),
+            Self::mirror_mask(self.board_mask),
+            self.num_moves,
+        )
+    }
+
+    /// Returns whichever of this board and its mirror image has the smaller [`key`], which is
+    /// the same board for both
+    ///
+    /// This is useful for storing only one of each pair of mirrored positions
+    ///
+    /// [`key`]: #method.key
+    pub fn canonical(&self) -> Self {
+        let mirror = self.mirror();
+        if mirror.key() < self.key() {
+            mirror
+        } else {
+            *self
+        }
+    }
+
+    /// Returns the smaller of the keys of this board and its mirror image, which is the same
+    /// for both boards
+    pub(crate) fn canonical_key(&self) -> u64 {
+        let key = self.key();
+        key.min(Self::mirror_mask(key))
+    }
+
+    /// Mirrors a mask of squares left-to-right
+    fn mirror_mask(mask: u64) -> u64 {
+        (0..W).fold(0, |mirrored, column| {
+            // include the spare bit above each column, which is set in the keys of full columns
+            let column_bits = (mask >> (column * (H + 1))) & ((1 << (H + 1)) - 1);
+            mirrored | column_bits << ((W - 1 - column) * (H + 1))
+        })
+    }
+
     /// Returns the Huffman code used for searching the opening database (see [Huffman Codes])
     /// 
     /// # Notes
-    /// For positions with more than 13 tiles, data will be lost and the returned code will not
-    /// be unique
+    /// For positions with more than 12 tiles, data will be lost and the returned code will not
+    /// be unique.
+    ///
+    /// The opening database only stores one orientation of each position, the smaller of this
+    /// code and [`BitBoard::huffman_code_mirror`]
     ///
     /// [Huffman Codes]: #huffman-codes
+    /// [`BitBoard::huffman_code_mirror`]: #method.huffman_code_mirror
     pub fn huffman_code(&self) -> u32 {
-        self._huffman_code(false).min(self._huffman_code(true))
+        self._huffman_code(false)
+    }
+
+    /// Returns the Huffman code of the mirror image of the position (see [Huffman Codes])
+    ///
+    /// [Huffman Codes]: #huffman-codes
+    pub fn huffman_code_mirror(&self) -> u32 {
+        self._huffman_code(true)
     }
 
     /// Returns Huffman code for opening database, optionally mirroring the position
@@ -385,15 +1530,15 @@ impl BitBoard {
         let mut code = 0;
 
         let iter: Box<dyn Iterator<Item = usize>> = if mirror {
-            Box::new((0..WIDTH).rev())
+            Box::new((0..W).rev())
         } else {
-            Box::new(0..WIDTH)
+            Box::new(0..W)
         };
         for column in iter {
             let column_mask = Self::column_mask(column);
             // go over the top of the columns to add a separator when a row is full
-            for row in 0..=HEIGHT {
-                let row_mask = static_masks::bottom_mask() << row;
+            for row in 0..=H {
+                let row_mask = static_masks::bottom_mask::<W, H>() << row;
                 let tile_mask = column_mask & row_mask;
 
                 // end of column
@@ -415,12 +1560,268 @@ impl BitBoard {
                 }
             }
         }
+        // the trailing 0 isn't needed to decode the columns, but the shipped opening database
+        // was generated with it. 12 tiles still fit: 7 separators + 24 tile bits + 1 = 32 bits
         code << 1
     }
 }
 
-impl Default for BitBoard {
+/// Methods that rely on tables precomputed for the standard board size
+impl BitBoard {
+    /// Returns a mask of the empty squares that can't be part of an alignment for either player,
+    /// because every four-in-a-row window through them contains tiles of both players
+    ///
+    /// # Notes
+    /// Once every empty square is dead, the game is a forced draw
+    pub fn dead_squares(&self) -> u64 {
+        let (player_one_mask, player_two_mask) = (self.tiles(Player::One), self.tiles(Player::Two));
+        let mut empty = static_masks::full_board_mask::<WIDTH, HEIGHT>() & !self.board_mask;
+        let mut dead = 0;
+
+        while empty != 0 {
+            // take the lowest square
+            let square = empty & empty.wrapping_neg();
+            empty ^= square;
+
+            if square_windows_of(square)
+                .all(|window| window & player_one_mask != 0 && window & player_two_mask != 0)
+            {
+                dead |= square;
+            }
+        }
+        dead
+    }
+}
+
+/// Returns an iterator over every position with `num_moves` tiles where the player to move can
+/// win immediately, for generating tactical puzzles
+///
+/// # Notes
+/// Only positions reachable by legal play without the game ending are included, and a position
+/// and its mirror image are only included once. Every position with `num_moves - 1` tiles is
+/// held in memory during enumeration, so the enumeration quickly becomes expensive. The number
+/// of positions for small numbers of moves are:
+///
+/// | Moves     | 0-5 | 6   | 7    | 8     | 9     |
+/// |-----------|-----|-----|------|-------|-------|
+/// | Positions | 0   | 593 | 1391 | 13580 | 29572 |
+pub fn positions_with_mate_in_one(num_moves: usize) -> impl Iterator<Item = BitBoard> {
+    let mut positions = vec![BitBoard::new()];
+
+    for _ in 0..num_moves.min(WIDTH * HEIGHT) {
+        let mut seen = BTreeSet::new();
+        let mut next_positions = Vec::new();
+
+        for board in positions {
+            for column in 0..WIDTH {
+                // the game ends with winning moves, so don't search any further
+                if !board.playable(column) || board.check_winning_move(column) {
+                    continue;
+                }
+                let mut next = board;
+                next.play(board.possible_moves() & BitBoard::column_mask(column));
+                if seen.insert(next.canonical_key()) {
+                    next_positions.push(next);
+                }
+            }
+        }
+        positions = next_positions;
+    }
+
+    positions.into_iter().filter(|board| {
+        (0..WIDTH).any(|column| board.playable(column) && board.check_winning_move(column))
+    })
+}
+
+/// An iterator over the positions reached by every sequence of `depth` moves in which neither
+/// player wins, in lexicographic order of the 0-indexed moves
+///
+/// # Notes
+/// A position is yielded once for every sequence of moves reaching it, so transpositions are
+/// repeated. Sequences are searched depth-first, so only `depth` boards are held at once and
+/// sequences through a winning move are skipped without trying their continuations
+///
+/// # Example
+/// ```
+/// use connect4_ai::bitboard::PositionsAtDepth;
+///
+/// // the second player can't win or block anything with their first move
+/// assert_eq!(PositionsAtDepth::new(2).count(), 49);
+/// assert_eq!(PositionsAtDepth::with_first_move(2, 3).count(), 7);
+/// ```
+pub struct PositionsAtDepth {
+    depth: usize,
+    // the columns the first move is chosen from
+    first_moves: core::ops::Range<usize>,
+    // the board after each move of the current sequence and the next column to try from it
+    stack: Vec<(BitBoard, usize)>,
+}
+
+impl PositionsAtDepth {
+    /// Creates an iterator over the positions after `depth` moves
+    pub fn new(depth: usize) -> Self {
+        Self::with_first_moves(depth, 0..WIDTH)
+    }
+
+    /// Creates an iterator over the positions after `depth` moves starting with a move in the
+    /// 0-indexed `column`, for splitting the positions between threads
+    ///
+    /// With a `depth` of 0, the empty board is still yielded
+    pub fn with_first_move(depth: usize, column: usize) -> Self {
+        Self::with_first_moves(depth, column..column + 1)
+    }
+
+    fn with_first_moves(depth: usize, first_moves: core::ops::Range<usize>) -> Self {
+        let mut stack = Vec::with_capacity(depth + 1);
+        stack.push((BitBoard::new(), first_moves.start));
+        Self {
+            depth,
+            first_moves,
+            stack,
+        }
+    }
+}
+
+impl Iterator for PositionsAtDepth {
+    type Item = BitBoard;
+
+    fn next(&mut self) -> Option<BitBoard> {
+        loop {
+            let num_moves = self.stack.len().checked_sub(1)?;
+            let last_column = if num_moves == 0 {
+                self.first_moves.end.min(WIDTH)
+            } else {
+                WIDTH
+            };
+            let (board, next_column) = self.stack.last_mut()?;
+            let board = *board;
+
+            if num_moves == self.depth {
+                self.stack.pop();
+                return Some(board);
+            }
+            if *next_column >= last_column {
+                // every continuation of this sequence has been tried
+                self.stack.pop();
+                continue;
+            }
+            let column = *next_column;
+            *next_column += 1;
+
+            // sequences end at the first winning move
+            if board.playable(column) && !board.check_winning_move(column) {
+                let mut next = board;
+                next.play_column_unchecked(column);
+                self.stack.push((next, 0));
+            }
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for GenericBitBoard<W, H> {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Parses a string of 1-indexed moves with [`BitBoard::from_moves`]
+///
+/// # Example
+/// ```
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use connect4_ai::bitboard::{BitBoard, BoardError};
+///
+/// let board = "112233".parse::<BitBoard>()?;
+/// assert!(board.check_winning_move(3));
+///
+/// assert_eq!("ill".parse::<BitBoard>().err(), Some(BoardError::ParseError('i')));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`BitBoard::from_moves`]: struct.GenericBitBoard.html#method.from_moves
+impl<const W: usize, const H: usize> core::str::FromStr for GenericBitBoard<W, H> {
+    type Err = BoardError;
+
+    fn from_str(moves: &str) -> Result<Self, BoardError> {
+        Self::from_moves(moves)
+    }
+}
+
+/// Creates a board from a slice of 0-indexed moves with [`BitBoard::from_slice_checked`]
+///
+/// # Example
+/// ```
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use connect4_ai::bitboard::{BitBoard, BoardError};
+/// use std::convert::TryFrom;
+///
+/// let board = BitBoard::try_from(&[0, 0, 1, 1, 2, 2][..])?;
+/// assert!(board.check_winning_move(3));
+///
+/// assert_eq!(
+///     BitBoard::try_from(&[3, 7][..]).err(),
+///     Some(BoardError::ColumnOutOfRange(7))
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`BitBoard::from_slice_checked`]: struct.GenericBitBoard.html#method.from_slice_checked
+impl<const W: usize, const H: usize> core::convert::TryFrom<&[usize]> for GenericBitBoard<W, H> {
+    type Error = BoardError;
+
+    fn try_from(moves: &[usize]) -> Result<Self, BoardError> {
+        Self::from_slice_checked(moves)
+    }
+}
+
+/// Boards are equal when they have the same tiles and move counter, so positions reached by
+/// different move orders compare equal
+impl<const W: usize, const H: usize> PartialEq for GenericBitBoard<W, H> {
+    fn eq(&self, other: &Self) -> bool {
+        // the threat masks are calculated from the tiles, so don't need comparing
+        self.player_mask == other.player_mask
+            && self.board_mask == other.board_mask
+            && self.num_moves == other.num_moves
+    }
+}
+
+impl<const W: usize, const H: usize> Eq for GenericBitBoard<W, H> {}
+
+/// Hashes the same fields compared by `PartialEq`
+impl<const W: usize, const H: usize> core::hash::Hash for GenericBitBoard<W, H> {
+    fn hash<S: core::hash::Hasher>(&self, state: &mut S) {
+        self.player_mask.hash(state);
+        self.board_mask.hash(state);
+        self.num_moves.hash(state);
+    }
+}
+
+/// Renders the board as a grid with the column numbers along the top, the top row first and
+/// player one's tiles as `X`, player two's tiles as `O` and empty squares as `.`
+impl<const W: usize, const H: usize> core::fmt::Display for GenericBitBoard<W, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for column in 0..W {
+            write!(f, "{}", column + 1)?;
+        }
+        let player_one = self.tiles(Player::One);
+        for row in (0..H).rev() {
+            writeln!(f)?;
+            for column in 0..W {
+                let square = 1 << (column * (H + 1) + row);
+                let tile = if self.board_mask & square == 0 {
+                    '.'
+                } else if player_one & square != 0 {
+                    'X'
+                } else {
+                    'O'
+                };
+                write!(f, "{}", tile)?;
+            }
+        }
+        Ok(())
+    }
+}
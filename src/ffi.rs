@@ -0,0 +1,215 @@
+//! A C interface to the board and solver, for calling from C or from Python with `ctypes`
+//!
+//! # Notes
+//! Build a shared library with `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//! The functions are declared in C as:
+//!
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//!
+//! typedef struct C4Board C4Board;
+//! typedef struct C4Solver C4Solver;
+//!
+//! #define C4_OK 0
+//! #define C4_ERROR_NULL_POINTER -1
+//! #define C4_ERROR_PANIC -2
+//!
+//! C4Board *c4_board_from_moves(const char *moves);
+//! int32_t c4_solve(C4Board *board, int32_t *score, int32_t *best_move);
+//! void c4_board_free(C4Board *board);
+//!
+//! C4Solver *c4_solver_new(size_t capacity);
+//! int32_t c4_solver_solve(C4Solver *solver, C4Board *board, int32_t *score, int32_t *best_move);
+//! void c4_solver_free(C4Solver *solver);
+//! ```
+//!
+//! Boards and solvers are opaque and must be freed with `c4_board_free` and `c4_solver_free`.
+//! `c4_solve` allocates a new transposition table on every call, so to solve several positions
+//! create one solver with `c4_solver_new` and solve them all with `c4_solver_solve`, which keeps
+//! the table between calls. No function unwinds into the caller, panics are caught and reported
+//! as errors instead
+
+use core::ffi::c_char;
+use std::ffi::CStr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{
+    bitboard::BitBoard,
+    solver::Solver,
+    transposition_table::{TranspositionTable, TABLE_MAX_SIZE},
+};
+
+/// The return code of a successful call
+pub const C4_OK: i32 = 0;
+/// The return code when a required pointer argument is null
+pub const C4_ERROR_NULL_POINTER: i32 = -1;
+/// The return code when the solver panicked
+pub const C4_ERROR_PANIC: i32 = -2;
+
+/// Creates a board from a null-terminated string of 1-indexed moves (see
+/// [`BitBoard::from_moves`])
+///
+/// Returns a null pointer if `moves` is null, isn't valid UTF-8 or is an invalid position
+///
+/// # Safety
+/// `moves` must be null or point to a null-terminated string
+///
+/// [`BitBoard::from_moves`]: ../bitboard/struct.GenericBitBoard.html#method.from_moves
+#[no_mangle]
+pub unsafe extern "C" fn c4_board_from_moves(moves: *const c_char) -> *mut BitBoard {
+    if moves.is_null() {
+        return core::ptr::null_mut();
+    }
+    let board = catch_unwind(|| {
+        // SAFETY: the caller guarantees a null-terminated string
+        let moves = unsafe { CStr::from_ptr(moves) }.to_str().ok()?;
+        BitBoard::from_moves(moves).ok()
+    });
+    match board {
+        Ok(Some(board)) => Box::into_raw(Box::new(board)),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// Solves a board, writing the score and the 0-indexed best move to `score` and `best_move`
+///
+/// Returns [`C4_OK`] on success, or an error code without writing the results. A full board
+/// scores 0 with a best move of 7, the width of the board
+///
+/// # Notes
+/// Each call allocates and drops a transposition table of the default size, use
+/// [`c4_solver_solve`] to solve several positions
+///
+/// # Safety
+/// `board` must be null or a board created by [`c4_board_from_moves`] that hasn't been freed,
+/// and `score` and `best_move` must be null or valid for writes
+///
+/// [`C4_OK`]: constant.C4_OK.html
+/// [`c4_board_from_moves`]: fn.c4_board_from_moves.html
+/// [`c4_solver_solve`]: fn.c4_solver_solve.html
+#[no_mangle]
+pub unsafe extern "C" fn c4_solve(
+    board: *mut BitBoard,
+    score: *mut i32,
+    best_move: *mut i32,
+) -> i32 {
+    if board.is_null() || score.is_null() || best_move.is_null() {
+        return C4_ERROR_NULL_POINTER;
+    }
+    // SAFETY: the caller guarantees a live board
+    let board = unsafe { *board };
+    // SAFETY: the caller guarantees both pointers are valid for writes
+    unsafe { write_solution(|| Solver::new(board).solve(), score, best_move) }
+}
+
+/// Frees a board created by [`c4_board_from_moves`], doing nothing for a null pointer
+///
+/// # Safety
+/// `board` must be null or a board created by [`c4_board_from_moves`] that hasn't already been
+/// freed
+///
+/// [`c4_board_from_moves`]: fn.c4_board_from_moves.html
+#[no_mangle]
+pub unsafe extern "C" fn c4_board_free(board: *mut BitBoard) {
+    if !board.is_null() {
+        // SAFETY: the caller guarantees the board came from `Box::into_raw` and is freed once
+        drop(unsafe { Box::from_raw(board) });
+    }
+}
+
+/// Creates a solver with a transposition table of `capacity` entries, or the default capacity
+/// of [`TABLE_MAX_SIZE`] entries if `capacity` is 0 (see [`TranspositionTable::with_capacity`])
+///
+/// The table is kept between calls to [`c4_solver_solve`]. Returns a null pointer if the
+/// solver couldn't be created
+///
+/// [`TABLE_MAX_SIZE`]: ../transposition_table/constant.TABLE_MAX_SIZE.html
+/// [`TranspositionTable::with_capacity`]: ../transposition_table/struct.TranspositionTable.html#method.with_capacity
+/// [`c4_solver_solve`]: fn.c4_solver_solve.html
+#[no_mangle]
+pub extern "C" fn c4_solver_new(capacity: usize) -> *mut Solver {
+    let capacity = if capacity == 0 {
+        TABLE_MAX_SIZE
+    } else {
+        capacity
+    };
+    let solver = catch_unwind(|| {
+        let table = TranspositionTable::with_capacity(capacity);
+        Solver::new_with_transposition_table(BitBoard::new(), table)
+    });
+    match solver {
+        Ok(solver) => Box::into_raw(Box::new(solver)),
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+/// Solves a board with a solver, writing the score and the 0-indexed best move to `score` and
+/// `best_move` as [`c4_solve`] does, and keeping the solver's transposition table for later
+/// calls
+///
+/// # Safety
+/// `solver` must be null or a solver created by [`c4_solver_new`] that hasn't been freed and
+/// isn't in use by another call, `board` must be null or a board created by
+/// [`c4_board_from_moves`] that hasn't been freed, and `score` and `best_move` must be null or
+/// valid for writes
+///
+/// [`c4_solve`]: fn.c4_solve.html
+/// [`c4_solver_new`]: fn.c4_solver_new.html
+/// [`c4_board_from_moves`]: fn.c4_board_from_moves.html
+#[no_mangle]
+pub unsafe extern "C" fn c4_solver_solve(
+    solver: *mut Solver,
+    board: *mut BitBoard,
+    score: *mut i32,
+    best_move: *mut i32,
+) -> i32 {
+    if solver.is_null() || board.is_null() || score.is_null() || best_move.is_null() {
+        return C4_ERROR_NULL_POINTER;
+    }
+    // SAFETY: the caller guarantees a live board and a live solver not used elsewhere
+    let (solver, board) = unsafe { (&mut *solver, *board) };
+    let solve = || {
+        solver.set_board(board);
+        solver.solve()
+    };
+    // SAFETY: the caller guarantees both pointers are valid for writes
+    unsafe { write_solution(solve, score, best_move) }
+}
+
+/// Frees a solver created by [`c4_solver_new`], doing nothing for a null pointer
+///
+/// # Safety
+/// `solver` must be null or a solver created by [`c4_solver_new`] that hasn't already been
+/// freed
+///
+/// [`c4_solver_new`]: fn.c4_solver_new.html
+#[no_mangle]
+pub unsafe extern "C" fn c4_solver_free(solver: *mut Solver) {
+    if !solver.is_null() {
+        // SAFETY: the caller guarantees the solver came from `Box::into_raw` and is freed once
+        drop(unsafe { Box::from_raw(solver) });
+    }
+}
+
+/// Runs `solve`, catching panics, and writes its results to `score` and `best_move`
+///
+/// # Safety
+/// `score` and `best_move` must be valid for writes
+unsafe fn write_solution(
+    solve: impl FnOnce() -> (i32, usize),
+    score: *mut i32,
+    best_move: *mut i32,
+) -> i32 {
+    match catch_unwind(AssertUnwindSafe(solve)) {
+        Ok((solved_score, solved_move)) => {
+            // SAFETY: the caller guarantees both pointers are valid for writes
+            unsafe {
+                *score = solved_score;
+                *best_move = solved_move as i32;
+            }
+            C4_OK
+        }
+        Err(_) => C4_ERROR_PANIC,
+    }
+}
@@ -6,36 +6,53 @@
 //! # Basic Usage
 //! 
 //! ```
-//! use connect4_ai::{solver::Solver, bitboard::BitBoard};
+//! use connect4_ai::solver::Solver;
 //!
 //!# use std::error::Error;
 //!# fn main() -> Result<(), Box<dyn Error>> {
-//! let mut solver = Solver::new(BitBoard::from_moves("112233")?);
+//! let mut solver = Solver::from_moves("112233")?;
 //! let (score, best_move) = solver.solve();
 //!
 //! assert!((score, best_move) == (18, 3));
 //!# Ok(())
 //!# }
 //! ```
+//!
+//! # Features
+//! - `std` (default): the standard library, for verbose logging and timed searches. Without it
+//!   the bitboard, solver and transposition tables build with `#![no_std]` and `alloc`
+//! - `native` (default): the opening database, opening book and parallel search
+//! - `serde`: serialization of boards
+//! - `ffi`: a C interface to the solver (see [`ffi`])
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
-use static_assertions::*;
 pub use anyhow;
 
 pub mod transposition_table;
 
 pub mod bitboard;
 
+#[cfg(feature = "native")]
 pub mod opening_database;
 
+#[cfg(feature = "native")]
+pub mod opening_book;
+
 pub mod solver;
 
+pub mod threat_map;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
 mod test;
 
-/// The width of the game board in tiles
+/// The width of the standard game board in tiles
 pub const WIDTH: usize = 7;
 
-/// The height of the game board in tiles
+/// The height of the standard game board in tiles
 pub const HEIGHT: usize = 6;
 
-// ensure that the given dimensions fit in a u64 for the bitboard representation
-const_assert!(WIDTH * (HEIGHT + 1) < 64);
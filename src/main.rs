@@ -1,17 +1,14 @@
 use anyhow::Result;
 
-use std::cmp::Ordering;
 use std::io::{stdin, stdout, Write};
 
-use connect4_ai::{transposition_table::*, opening_database::*, solver::*, bitboard::*};
+use connect4_ai::{opening_database::*, solver::*, bitboard::*};
 
 mod arrayboard;
 use arrayboard::*;
 
 fn main() -> Result<()> {
     let mut board = ArrayBoard::new();
-    // keep the transposition table out here so we can re-use it
-    let transposition_table = TranspositionTable::new();
 
     let stdin = stdin();
 
@@ -19,7 +16,7 @@ fn main() -> Result<()> {
 
     // check for opening database
     let mut opening_database: Option<OpeningDatabase> = None;
-    let opening_database_result = OpeningDatabase::load();
+    let opening_database_result = OpeningDatabase::map();
     match opening_database_result {
         Ok(database) => {
             opening_database = Some(database);
@@ -55,6 +52,12 @@ fn main() -> Result<()> {
         },
     }
 
+    // keep one solver for the whole game so the transposition table is re-used
+    let mut solver = Solver::new(BitBoard::new());
+    if let Some(database) = opening_database {
+        solver = solver.with_opening_database(database);
+    }
+
     let mut ai_players = (false, false);
 
     // choose AI control of player 1
@@ -106,36 +109,13 @@ fn main() -> Result<()> {
                             std::thread::sleep(std::time::Duration::new(3, 0));
                         }
 
-                        let mut solver = Solver::new_with_transposition_table(
-                            BitBoard::from_moves(&board.game)?,
-                            transposition_table.clone(),
-                        );
-                        if let Some(database) = opening_database.clone() {
-                            solver = solver.with_opening_database(database);
-                        }
-
-                        let (score, best_move) = solver.solve();
-
-                        let win_distance = solver.score_to_win_distance(score);
-                        let move_string = if win_distance == 1 {"move"} else {"moves"};
-                        match score.cmp(&0) {
-                            Ordering::Greater =>  {
-                                let player = if board.player_one { 1 } else { 2 };
-                                println!("Player {} can force a win in at most {} {}.", player, win_distance, move_string);
-                            },
-                            Ordering::Less => {
-                                let player = if board.player_one { 2 } else { 1 };
-                                println!("Player {} can force a win in at most {} {}.", player, win_distance, move_string);
-                                
-                            },
-                            Ordering::Equal => {
-                                let player = if board.player_one { 1 } else { 2 };
-                                println!("Player {} can at best force a draw, {} {} remaining", player, win_distance, move_string);
-                            }
-                        }
+                        solver.set_board(BitBoard::from_moves(&board.game)?);
+                        let outcome = solver.outcome();
+                        let player = if board.player_one { Player::One } else { Player::Two };
+                        println!("{}", outcome.description(player));
 
-                        println!("Best move: {}", best_move + 1);
-                        best_move + 1
+                        println!("Best move: {}", outcome.best_move + 1);
+                        outcome.best_move + 1
 
                     // human player
                     } else {
@@ -0,0 +1,141 @@
+//! A configurable book of solved positions, keyed by the moves that reach them
+//!
+
+use anyhow::{anyhow, Result};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+
+use crate::{bitboard::*, solver::*, transposition_table::TranspositionStore, HEIGHT, WIDTH};
+
+/// A book of positions reached by given move sequences, with their scores and best moves
+///
+/// # Notes
+/// Unlike the [`OpeningDatabase`], which stores every position at a fixed depth by Huffman code,
+/// a book only stores the lines it was built from, at any depth. Positions reached by different
+/// move orders are stored separately.
+///
+/// Books are saved as a sequence of entries, each storing the number of moves, the 0-indexed
+/// moves, the score and the best move in 1 byte each.
+///
+/// # Example
+/// ```no_run
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use connect4_ai::{opening_book::OpeningBook, opening_database::OpeningDatabase, solver::Solver};
+///
+/// let database = OpeningDatabase::load()?;
+/// let book = OpeningBook::build(&["4444", "4453"], |board| {
+///     Solver::new(board).with_opening_database(database.clone())
+/// })?;
+/// book.save("book.bin")?;
+///
+/// let book = OpeningBook::load("book.bin")?;
+/// let (score, best_move) = book.get(&[3, 3, 3, 3]).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`OpeningDatabase`]: ../opening_database/struct.OpeningDatabase.html
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+    entries: BTreeMap<Vec<usize>, (i32, usize)>,
+}
+
+impl OpeningBook {
+    /// Creates an empty book
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a book by solving the position at the end of each line
+    ///
+    /// Lines are strings of 1-indexed moves, as for [`BitBoard::from_moves`]. `solver_factory`
+    /// creates the `Solver` for each position, so an opening database or shared transposition
+    /// table can be attached
+    ///
+    /// Returns `Err` if any line is an invalid position
+    ///
+    /// [`BitBoard::from_moves`]: ../bitboard/struct.GenericBitBoard.html#method.from_moves
+    pub fn build<T, F>(lines: &[&str], mut solver_factory: F) -> Result<Self>
+    where
+        T: TranspositionStore,
+        F: FnMut(BitBoard) -> GenericSolver<WIDTH, HEIGHT, T>,
+    {
+        let mut book = Self::new();
+        for line in lines.iter() {
+            let board = BitBoard::from_moves(line)?;
+            // from_moves has already checked every character is a valid column
+            let moves = line
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .map(|column| column as usize - 1)
+                .collect();
+
+            let entry = solver_factory(board).solve();
+            book.entries.insert(moves, entry);
+        }
+        Ok(book)
+    }
+
+    /// Retrieve the score and best move (0-indexed) of the position reached by a sequence of
+    /// 0-indexed moves
+    ///
+    /// Returns `None` if the sequence isn't in the book
+    pub fn get(&self, moves: &[usize]) -> Option<(i32, usize)> {
+        self.entries.get(moves).copied()
+    }
+
+    /// Returns the number of lines stored in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the book stores no lines
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Try to write the book to the given file path
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for (moves, &(score, best_move)) in self.entries.iter() {
+            file.write_u8(moves.len() as u8)?;
+            for &column in moves.iter() {
+                file.write_u8(column as u8)?;
+            }
+            file.write_i8(score as i8)?;
+            file.write_u8(best_move as u8)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Try to load a book from the given file path
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut book = Self::new();
+        loop {
+            let num_moves = match file.read_u8() {
+                Ok(num_moves) => num_moves as usize,
+                // the file ends between entries
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error.into()),
+            };
+            if num_moves > WIDTH * HEIGHT {
+                return Err(anyhow!("Invalid book, entry has {} moves", num_moves));
+            }
+            let mut moves = vec![0; num_moves];
+            for column in moves.iter_mut() {
+                *column = file.read_u8()? as usize;
+            }
+            let score = file.read_i8()? as i32;
+            let best_move = file.read_u8()? as usize;
+            book.entries.insert(moves, (score, best_move));
+        }
+        Ok(book)
+    }
+}
@@ -1,211 +1,680 @@
 //! A searchable store of Connect 4 positions to speed up early-game searches
 //!
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use indicatif::*;
+use memmap2::Mmap;
 use rayon::prelude::*;
 
-use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Read};
-use std::rc::Rc;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::*;
+use std::sync::Arc;
 use std::thread;
 use std::time::*;
 
-use crate::{bitboard::*, solver::*, HEIGHT, WIDTH};
+use crate::{bitboard::*, solver::*, WIDTH};
 
 /// Hard-coded database path
 pub const DATABASE_PATH: &str = "opening_database.bin";
 /// Hard-coded temp file path
 pub const TEMP_FILE_PATH: &str = "temp_positions.bin";
-/// Hard-coded database depth
+/// Hard-coded checkpoint file path for scored positions
+pub const CHECKPOINT_FILE_PATH: &str = "temp_scores.bin";
+/// Hard-coded database depth, also assumed for database files without a header
 pub const DATABASE_DEPTH: usize = 12;
 /// Hard-coded database size
 pub const DATABASE_NUM_POSITIONS: usize = 4200899;
 
-/// A shared, immutable, non-thread-safe opening database
+/// The bytes that start a database file header, followed by 1 byte for the database depth
+const DATABASE_MAGIC: [u8; 4] = *b"C4DB";
+
+/// The size of each database entry in bytes, a 4 byte position code and a 1 byte score
+const ENTRY_SIZE: usize = 5;
+
+/// A generated position as its Huffman code, player mask and board mask
+type Position = (u32, u64, u64);
+
+/// The size of each position in the temp file in bytes
+const POSITION_SIZE: usize = 20;
+
+/// A shared, immutable, thread-safe opening database
 ///
 /// # Notes
-/// The database stores all 'unique' positions with exactly 12 tiles played and their scores.
+/// The database stores all 'unique' positions with exactly [`depth`] tiles played (12 for the
+/// hard-coded database) and their scores.
 /// In this case 'unique' means a position whose mirror image is not already in the database
 /// and does not have any moves that end the game on the next turn, as the game-tree search
 /// short-circuits in these cases before checking the database.
 ///
 /// Positions are stored using a Huffman code of the board (4 bytes) and 1 byte representing
 /// the signed score, for a total size of ~20MB. The entries are stored in ascending numeric order
-/// of the Huffman code to allow binary search.
+/// of the Huffman code to allow binary search. Generated database files start with a 5 byte
+/// header of `C4DB` and the depth.
 ///
 /// For details of the Huffman code and score, see [`BitBoard`] and [`Solver`].
 ///
-/// The database contains a `Rc` internally, allowing cheap cloning.
+/// The database contains an `Arc` internally, allowing cheap cloning and sharing between threads.
 ///
-/// [`BitBoard`]: ../bitboard/struct.BitBoard.html#huffman-codes
-/// [`Solver`]: ../solver/struct.Solver.html#position-scoring
+/// [`depth`]: #method.depth
+/// [`BitBoard`]: ../bitboard/struct.GenericBitBoard.html#huffman-codes
+/// [`Solver`]: ../solver/struct.GenericSolver.html#position-scoring
 #[derive(Clone)]
-pub struct OpeningDatabase(Rc<OpeningDatabaseStorage>);
+pub struct OpeningDatabase(Arc<OpeningDatabaseStorage>);
 
 impl OpeningDatabase {
     /// Try to load a database from the hard-coded file path into memory
     pub fn load() -> Result<Self> {
-        Ok(Self(Rc::new(OpeningDatabaseStorage::load()?)))
+        Self::load_from(DATABASE_PATH)
+    }
+
+    /// Try to load a database from the given file path into memory
+    ///
+    /// The number of positions is derived from the length of the file, and the depth is read
+    /// from the file header. Files without a header are assumed to be at [`DATABASE_DEPTH`]
+    ///
+    /// [`DATABASE_DEPTH`]: constant.DATABASE_DEPTH.html
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self(Arc::new(OpeningDatabaseStorage::load(path.as_ref())?)))
     }
 
-    /// Retrieve the score for a position, given as a huffman code
+    /// Try to memory-map a database from the hard-coded file path
+    pub fn map() -> Result<Self> {
+        Self::map_from(DATABASE_PATH)
+    }
+
+    /// Try to memory-map a database from the given file path
     ///
-    /// Returns `None` if the position is not found in the database, 
-    /// see [Notes] for details of stored positions
+    /// # Notes
+    /// Entries are read from the mapped file as they are searched instead of being copied into
+    /// memory, and every clone of the database shares the one mapping. Like [`load_from`], the
+    /// order of the entries is checked once when mapping. The file must not be modified while it
+    /// is mapped
+    ///
+    /// [`load_from`]: #method.load_from
+    pub fn map_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self(Arc::new(OpeningDatabaseStorage::map(path.as_ref())?)))
+    }
+
+    /// Returns the number of tiles played in each stored position
+    pub fn depth(&self) -> usize {
+        self.0.depth
+    }
+
+    /// Returns the number of positions stored in the database
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the database stores no positions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Retrieve the score for a position, given as the huffman codes of the position and its
+    /// mirror image
+    ///
+    /// Only one orientation of each position is stored, so both codes are searched for.
+    /// Returns `None` if the position is not found in the database,
+    /// see [Notes] for details of stored positions. Any score, including negative scores like
+    /// `-1`, is a stored position
     ///
     /// [Notes]: #Notes
-    pub fn get(&self, position_code: u32) -> Option<i32> {
-        self.0.get(position_code)
+    pub fn get(&self, code: u32, mirror_code: u32) -> Option<i32> {
+        self.0.get(code).or_else(|| self.0.get(mirror_code))
+    }
+
+    /// Retrieve the scores of many positions in parallel
+    ///
+    /// Returns the scores in the same order as `boards`, with `None` for positions that aren't
+    /// at the database depth or aren't found in the database
+    pub fn label_positions(&self, boards: &[BitBoard]) -> Vec<Option<i8>> {
+        boards
+            .par_iter()
+            .map(|board| {
+                if board.num_moves() == self.depth() {
+                    self.get(board.huffman_code(), board.huffman_code_mirror())
+                        .map(|score| score as i8)
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
 
     /// Generate an opening database at the hard-coded depth and path
     ///
+    /// See [`DatabaseGenerator`] to generate a database at a different depth or path
+    ///
     /// # Warning
     /// This procedure is very computationally intensive; tested on a
     /// Ryzen 5 1600 @ 3.2GHz generation took 23 hours at 100% CPU usage on all cores
+    ///
+    /// [`DatabaseGenerator`]: struct.DatabaseGenerator.html
     pub fn generate() -> Result<()> {
+        DatabaseGenerator::new().generate()
+    }
+
+    /// Generate an opening database at the hard-coded depth and path, keeping the generated
+    /// positions within roughly `max_memory_bytes` of memory
+    ///
+    /// See [`DatabaseGenerator::generate_bounded`] for details
+    ///
+    /// [`DatabaseGenerator::generate_bounded`]: struct.DatabaseGenerator.html#method.generate_bounded
+    pub fn generate_bounded(max_memory_bytes: usize) -> Result<()> {
+        DatabaseGenerator::new().generate_bounded(max_memory_bytes)
+    }
+
+    /// Generate an opening database at the given depth directly into memory, without writing
+    /// any files or reporting progress
+    ///
+    /// See [`DatabaseGenerator`] to supply a deeper database for scoring, which is essential
+    /// for shallow depths
+    ///
+    /// # Warning
+    /// Without a deeper database every position is solved from scratch, which can take minutes
+    /// per position at shallow depths
+    ///
+    /// [`DatabaseGenerator`]: struct.DatabaseGenerator.html
+    pub fn generate_in_memory(depth: usize) -> Result<Self> {
+        DatabaseGenerator::new().depth(depth).generate_in_memory()
+    }
+}
+
+/// A configurable generator for opening databases
+///
+/// # Notes
+/// By default the generator uses the hard-coded depth and file paths. Generating a shallower
+/// database is much cheaper to enumerate but each position is much more expensive to score, so
+/// an existing deeper database can be supplied to speed up scoring.
+///
+/// # Example
+/// ```no_run
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use connect4_ai::opening_database::{DatabaseGenerator, OpeningDatabase};
+///
+/// DatabaseGenerator::new()
+///     .depth(4)
+///     .path("depth_4.bin")
+///     .temp_path("depth_4_positions.bin")
+///     .with_opening_database(OpeningDatabase::load()?)
+///     .generate()?;
+///
+/// let database = OpeningDatabase::load_from("depth_4.bin")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DatabaseGenerator {
+    depth: usize,
+    path: PathBuf,
+    temp_path: PathBuf,
+    checkpoint_path: PathBuf,
+    opening_database: Option<OpeningDatabase>,
+}
+
+impl DatabaseGenerator {
+    /// Creates a generator using the hard-coded depth and file paths
+    pub fn new() -> Self {
+        Self {
+            depth: DATABASE_DEPTH,
+            path: PathBuf::from(DATABASE_PATH),
+            temp_path: PathBuf::from(TEMP_FILE_PATH),
+            checkpoint_path: PathBuf::from(CHECKPOINT_FILE_PATH),
+            opening_database: None,
+        }
+    }
+
+    /// Sets the number of tiles played in each stored position
+    ///
+    /// # Notes
+    /// Huffman codes are only unique for positions with up to 12 tiles (see [`BitBoard`])
+    ///
+    /// [`BitBoard`]: ../bitboard/struct.GenericBitBoard.html#huffman-codes
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the path the finished database is written to
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the path of the temp file used to store generated positions before scoring
+    pub fn temp_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.temp_path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the path of the checkpoint file that scores are written to as they are calculated
+    pub fn checkpoint_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.checkpoint_path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Adds an existing opening database used when scoring the generated positions
+    pub fn with_opening_database(mut self, opening_database: OpeningDatabase) -> Self {
+        self.opening_database = Some(opening_database);
+        self
+    }
+
+    /// Generate an opening database with the configured depth and paths
+    ///
+    /// If the temp file already exists, the positions stored in it are scored instead of
+    /// generating new ones. Scores are saved to the checkpoint file as they are calculated, and
+    /// if it already exists the positions scored in it are skipped, so an interrupted generation
+    /// can be resumed. The checkpoint file is removed once the database is written
+    pub fn generate(&self) -> Result<()> {
         let start = Instant::now();
-        let mut next_time = start;
 
-        let mut positions = Vec::new();
+        let positions;
 
         // try to read positions from temp file
-        if std::path::Path::new(TEMP_FILE_PATH).exists() {
-            println!("Loading stored positions from {}", TEMP_FILE_PATH);
-            let mut positions_file = BufReader::new(File::open(TEMP_FILE_PATH)?);
-            for _ in 0..DATABASE_NUM_POSITIONS {
-                positions.push((
-                    positions_file.read_u32::<BigEndian>()?,
-                    positions_file.read_u64::<BigEndian>()?,
-                    positions_file.read_u64::<BigEndian>()?,
-                ));
-            }
+        if self.temp_path.exists() {
+            println!("Loading stored positions from {}", self.temp_path.display());
+            positions = self.read_positions()?;
         } else {
-            enum Message {
-                Count(usize),
-                // remaining positions generated, Vec<huffman code, player mask, board mask>
-                Finish((usize, Vec<(u32, u64, u64)>)),
+            positions = self.generate_positions(false)?;
+            print!("Writing out positions to {} ... ", self.temp_path.display());
+
+            let mut positions_file = BufWriter::new(
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&self.temp_path)?,
+            );
+
+            for position in positions.iter() {
+                write_position(&mut positions_file, *position)?;
             }
-            let (tx, rx) = channel();
-
-            for i in 0..WIDTH {
-                let tx = tx.clone();
-
-                thread::spawn(move || {
-                    let mut moves = [0; DATABASE_DEPTH];
-                    moves[0] = i;
-                    let mut positions = Vec::new();
-                    let mut generated = 0usize;
-                    let mut last_size = 0;
-                    let mut next_time = start + Duration::from_millis(100);
-
-                    loop {
-                        let mut iter = moves.iter().skip(1).take(HEIGHT + 1);
-                        if iter.all(|&x| x == WIDTH - 1) {
-                            tx.send(Message::Finish((generated, positions))).unwrap();
-                            break;
-                        }
 
-                        if let Ok(board) = BitBoard::from_slice(&moves) {
-                            // don't include next-turn wins, the tree search short-circuits these
-                            // before searching the database
-                            if !move_order()
-                                .iter()
-                                .any(|&i| board.playable(i) && board.check_winning_move(i))
-                            {
-                                // both mirrors will push the same huffman code, we will dedup later
-                                positions.push((
-                                    board.huffman_code(),
-                                    board.player_mask(),
-                                    board.board_mask(),
-                                ));
-                                generated += 1;
-                            }
-                        }
+            println!("Complete");
+        }
 
-                        moves[DATABASE_DEPTH - 1] += 1;
-                        // carry the addition
-                        for d in (0..DATABASE_DEPTH).rev() {
-                            if moves[d] >= WIDTH {
-                                moves[d] = 0;
-                                // d-1 should never underflow since the loop ends before that point is reached
-                                moves[d - 1] += 1;
-                            }
-                        }
-                        if Instant::now() > next_time {
-                            if positions.len() - last_size > 10_000_000 {
-                                positions.sort_unstable();
-                                positions.dedup_by(|a, b| a.0 == b.0);
-                                last_size = positions.len();
-                            }
-                            tx.send(Message::Count(generated)).unwrap();
-                            generated = 0;
-                            next_time += Duration::from_millis(500);
-                        }
-                    }
-                });
+        self.write_database(positions, start)
+    }
+
+    /// Generate an opening database with the configured depth and paths, keeping the generated
+    /// positions within roughly `max_memory_bytes` of memory
+    ///
+    /// # Notes
+    /// Generated positions are sorted and written to disk in batches next to the temp file,
+    /// then merged into the temp file, so only the unique positions are held in memory for
+    /// scoring. As with [`generate`], an existing temp file is scored instead and an existing
+    /// checkpoint file is resumed
+    ///
+    /// [`generate`]: #method.generate
+    pub fn generate_bounded(&self, max_memory_bytes: usize) -> Result<()> {
+        let start = Instant::now();
+
+        if self.temp_path.exists() {
+            println!("Loading stored positions from {}", self.temp_path.display());
+        } else {
+            // the worker threads and the main thread may all hold a full batch at once
+            let batch_len =
+                (max_memory_bytes / (std::mem::size_of::<Position>() * (WIDTH + 2))).max(1);
+
+            let mut batch = Vec::new();
+            let mut runs = Vec::new();
+            self.enumerate_positions(false, batch_len, |mut positions| {
+                batch.append(&mut positions);
+                if batch.len() >= batch_len {
+                    runs.push(self.write_run(&mut batch, runs.len())?);
+                }
+                Ok(())
+            })?;
+            if !batch.is_empty() {
+                runs.push(self.write_run(&mut batch, runs.len())?);
             }
 
-            let progress = ProgressBar::new(8532690438);
-            progress.set_style(
-                ProgressStyle::default_bar()
-                    .template(
-                        "[1/2] Generating positions: {bar:40.cyan/blue} {msg} ~{eta} remaining",
-                    )
-                    .progress_chars("█▓▒░  "),
+            print!(
+                "Merging {} batches of positions into {} ... ",
+                runs.len(),
+                self.temp_path.display()
+            );
+            let unique = self.merge_runs(&runs)?;
+            for (path, _) in runs.iter() {
+                std::fs::remove_file(path)?;
+            }
+            println!("Complete");
+            println!(
+                "Position generation complete in {:.1}s, found {} unique positions",
+                start.elapsed().as_secs_f64(),
+                unique,
             );
+        }
 
-            let mut generated = 0usize;
+        let positions = self.read_positions()?;
+        self.write_database(positions, start)
+    }
 
-            let mut finished = 0;
-            while finished < WIDTH {
-                match rx.recv()? {
-                    Message::Count(num) => generated += num,
-                    Message::Finish((thread_generated, mut thread_positions)) => {
-                        generated += thread_generated;
-                        positions.append(&mut thread_positions);
-                        positions.sort_unstable();
-                        positions.dedup_by(|a, b| a.0 == b.0);
+    /// Generate an opening database with the configured depth and return it without writing
+    /// any files or reporting progress
+    ///
+    /// The configured paths are ignored, so positions are always generated from scratch
+    pub fn generate_in_memory(&self) -> Result<OpeningDatabase> {
+        let positions = self.generate_positions(true)?;
+        let entries = self.score_positions(positions, true, None)?;
+
+        let (positions, values) = entries.into_iter().unzip();
+        Ok(OpeningDatabase(Arc::new(OpeningDatabaseStorage {
+            depth: self.depth,
+            entries: DatabaseEntries::InMemory { positions, values },
+        })))
+    }
 
-                        finished += 1;
-                    }
-                }
-                if Instant::now() > next_time {
-                    progress.set_position(generated as u64);
-                    progress.set_message(&format!(
-                        "({}M / {}M)",
-                        progress.position() / 1_000_000,
-                        progress.length() / 1_000_000
-                    ));
-                    next_time += Duration::from_millis(100);
-                }
+    /// Scores the positions and writes the finished database to the configured path
+    fn write_database(&self, positions: Vec<Position>, start: Instant) -> Result<()> {
+        let entries = self.score_positions(positions, false, Some(&self.checkpoint_path))?;
+
+        print!(
+            "Calculations complete, writing out to {} ... ",
+            self.path.display()
+        );
+
+        let mut file = BufWriter::new(
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&self.path)?,
+        );
+
+        file.write_all(&DATABASE_MAGIC)?;
+        file.write_u8(self.depth as u8)?;
+        for entry in entries {
+            file.write_u32::<BigEndian>(entry.0)?;
+            file.write_i8(entry.1)?;
+        }
+        file.flush()?;
+        println!("Complete");
+
+        // the scores are all in the database now
+        std::fs::remove_file(&self.checkpoint_path)?;
+
+        let finish = Instant::now();
+        println!(
+            "Opening database generation completed in {}",
+            HumanDuration(finish - start)
+        );
+
+        Ok(())
+    }
+
+    /// Reads the positions stored in the temp file
+    fn read_positions(&self) -> Result<Vec<Position>> {
+        let positions_file = File::open(&self.temp_path)?;
+        let num_positions = positions_file.metadata()?.len() as usize / POSITION_SIZE;
+        let mut positions_file = BufReader::new(positions_file);
+        (0..num_positions)
+            .map(|_| read_position(&mut positions_file))
+            .collect()
+    }
+
+    /// Sorts and deduplicates a batch of positions and writes it to the `index`th run file next
+    /// to the temp file, returning the path and length of the run
+    fn write_run(&self, batch: &mut Vec<Position>, index: usize) -> Result<(PathBuf, usize)> {
+        batch.sort_unstable();
+        batch.dedup_by(|a, b| a.0 == b.0);
+
+        let path = self.temp_path.with_extension(format!("run{}", index));
+        let mut file = BufWriter::new(File::create(&path)?);
+        let len = batch.len();
+        for position in batch.drain(..) {
+            write_position(&mut file, position)?;
+        }
+        file.flush()?;
+        Ok((path, len))
+    }
+
+    /// Merges sorted run files into the temp file, dropping duplicates, and returns the number
+    /// of unique positions
+    fn merge_runs(&self, runs: &[(PathBuf, usize)]) -> Result<usize> {
+        let mut readers = Vec::new();
+        // the next position of every run, lowest first
+        let mut heads = BinaryHeap::new();
+        for (i, (path, len)) in runs.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            if *len > 0 {
+                heads.push(Reverse((read_position(&mut reader)?, i)));
             }
+            readers.push((reader, len.saturating_sub(1)));
+        }
 
-            let finish = Instant::now();
-            progress.finish();
+        let mut positions_file = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&self.temp_path)?,
+        );
+        let mut last_code = None;
+        let mut unique = 0;
+        while let Some(Reverse((position, i))) = heads.pop() {
+            if last_code != Some(position.0) {
+                write_position(&mut positions_file, position)?;
+                last_code = Some(position.0);
+                unique += 1;
+            }
+            let (reader, remaining) = &mut readers[i];
+            if *remaining > 0 {
+                *remaining -= 1;
+                heads.push(Reverse((read_position(reader)?, i)));
+            }
+        }
+        positions_file.flush()?;
+        Ok(unique)
+    }
+
+    /// Enumerates every unique position at the configured depth that doesn't have a next-turn
+    /// win, returning the sorted Huffman code, player mask and board mask of each
+    fn generate_positions(&self, silent: bool) -> Result<Vec<Position>> {
+        let start = Instant::now();
+        let mut positions = Vec::new();
+
+        self.enumerate_positions(silent, usize::MAX, |mut thread_positions| {
+            positions.append(&mut thread_positions);
+            positions.sort_unstable();
+            positions.dedup_by(|a, b| a.0 == b.0);
+            Ok(())
+        })?;
+
+        if !silent {
             println!(
                 "Position generation complete in {:.1}s, found {} unique positions",
-                (finish - start).as_secs_f64(),
+                start.elapsed().as_secs_f64(),
                 positions.len(),
             );
-            print!("Writing out positions to {} ... ", TEMP_FILE_PATH);
+        }
+        Ok(positions)
+    }
 
-            let mut positions_file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(TEMP_FILE_PATH)?;
+    /// Enumerates every position at the configured depth that doesn't have a next-turn win on
+    /// one thread per first move, passing them to `on_batch` in sorted batches of at least
+    /// `batch_len` positions, or fewer once a thread has finished
+    ///
+    /// Each batch is deduplicated, but positions can be repeated across batches
+    fn enumerate_positions(
+        &self,
+        silent: bool,
+        batch_len: usize,
+        mut on_batch: impl FnMut(Vec<Position>) -> Result<()>,
+    ) -> Result<()> {
+        let depth = self.depth;
+        let start = Instant::now();
+        let mut next_time = start;
 
-            for position in positions.iter() {
-                positions_file.write_u32::<BigEndian>(position.0)?;
-                positions_file.write_u64::<BigEndian>(position.1)?;
-                positions_file.write_u64::<BigEndian>(position.2)?;
+        enum Message {
+            Count(usize),
+            // a full batch of positions generated, Vec<huffman code, player mask, board mask>
+            Batch(Vec<Position>),
+            // remaining positions generated
+            Finish((usize, Vec<Position>)),
+        }
+        // senders wait for each message to be received, so at most one batch per thread is
+        // held at once
+        let (tx, rx) = sync_channel(0);
+
+        for i in 0..WIDTH {
+            let tx = tx.clone();
+
+            thread::spawn(move || {
+                let mut positions = Vec::new();
+                let mut generated = 0usize;
+                let mut last_size = 0;
+                let mut next_time = start + Duration::from_millis(100);
+
+                for board in PositionsAtDepth::with_first_move(depth, i) {
+                    // don't include next-turn wins, the tree search short-circuits these
+                    // before searching the database
+                    if !move_order()
+                        .iter()
+                        .any(|&i| board.playable(i) && board.check_winning_move(i))
+                    {
+                        // both mirrors will push the same huffman code, we will dedup later
+                        positions.push((
+                            board.huffman_code().min(board.huffman_code_mirror()),
+                            board.player_mask(),
+                            board.board_mask(),
+                        ));
+                        generated += 1;
+
+                        if positions.len() >= batch_len {
+                            positions.sort_unstable();
+                            positions.dedup_by(|a, b| a.0 == b.0);
+                            tx.send(Message::Batch(std::mem::take(&mut positions)))
+                                .unwrap();
+                            last_size = 0;
+                        }
+                    }
+
+                    if Instant::now() > next_time {
+                        if positions.len() - last_size > 10_000_000 {
+                            positions.sort_unstable();
+                            positions.dedup_by(|a, b| a.0 == b.0);
+                            last_size = positions.len();
+                        }
+                        tx.send(Message::Count(generated)).unwrap();
+                        generated = 0;
+                        next_time += Duration::from_millis(500);
+                    }
+                }
+                // every sequence starting with this thread's first move has been tried
+                positions.sort_unstable();
+                positions.dedup_by(|a, b| a.0 == b.0);
+                tx.send(Message::Finish((generated, positions))).unwrap();
+            });
+        }
+
+        // the number of legal move sequences is only known for the hard-coded depth
+        let num_sequences = if depth == DATABASE_DEPTH {
+            8532690438
+        } else {
+            WIDTH.pow(depth as u32) as u64
+        };
+        let progress = if silent {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(num_sequences)
+        };
+        progress.set_style(
+            ProgressStyle::default_bar()
+                .template("[1/2] Generating positions: {bar:40.cyan/blue} {msg} ~{eta} remaining")
+                .progress_chars("█▓▒░  "),
+        );
+
+        let mut generated = 0usize;
+
+        let mut finished = 0;
+        while finished < WIDTH {
+            match rx.recv()? {
+                Message::Count(num) => generated += num,
+                Message::Batch(thread_positions) => on_batch(thread_positions)?,
+                Message::Finish((thread_generated, thread_positions)) => {
+                    generated += thread_generated;
+                    on_batch(thread_positions)?;
+
+                    finished += 1;
+                }
             }
+            if Instant::now() > next_time {
+                progress.set_position(generated as u64);
+                progress.set_message(&format!(
+                    "({}M / {}M)",
+                    progress.position() / 1_000_000,
+                    progress.length() / 1_000_000
+                ));
+                next_time += Duration::from_millis(100);
+            }
+        }
 
-            println!("Complete");
+        progress.finish();
+        Ok(())
+    }
+
+    /// Reads the scores stored in a checkpoint file, dropping any partially written entry at
+    /// the end of the file so that new entries can be appended
+    fn read_checkpoint(path: &Path) -> Result<Vec<(u32, i8)>> {
+        let checkpoint_file = OpenOptions::new().read(true).write(true).open(path)?;
+        let num_entries = checkpoint_file.metadata()?.len() as usize / ENTRY_SIZE;
+        checkpoint_file.set_len((num_entries * ENTRY_SIZE) as u64)?;
+
+        let mut checkpoint_file = BufReader::new(checkpoint_file);
+        (0..num_entries)
+            .map(|_| {
+                Ok((
+                    checkpoint_file.read_u32::<BigEndian>()?,
+                    checkpoint_file.read_i8()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Solves every generated position, returning the sorted Huffman codes and scores
+    ///
+    /// If a checkpoint path is given, the positions already scored in it are skipped and new
+    /// scores are appended to it as they are calculated
+    fn score_positions(
+        &self,
+        mut positions: Vec<Position>,
+        silent: bool,
+        checkpoint: Option<&Path>,
+    ) -> Result<Vec<(u32, i8)>> {
+        let depth = self.depth;
+        let mut next_time = Instant::now();
+        let num_positions = positions.len();
+
+        let mut entries = Vec::new();
+        let mut checkpoint_file = None;
+        if let Some(path) = checkpoint {
+            if path.exists() {
+                entries = Self::read_checkpoint(path)?;
+                entries.sort_unstable();
+                positions.retain(|position| {
+                    entries
+                        .binary_search_by_key(&position.0, |entry| entry.0)
+                        .is_err()
+                });
+                if !silent {
+                    println!(
+                        "Resuming from {}, {} positions already scored",
+                        path.display(),
+                        entries.len()
+                    );
+                }
+            }
+            checkpoint_file = Some(BufWriter::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            ));
         }
 
         enum Message2 {
@@ -214,21 +683,30 @@ impl OpeningDatabase {
         }
         let (tx, rx) = channel();
 
-        let progress = ProgressBar::new(positions.len() as u64);
+        let progress = if silent {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(num_positions as u64)
+        };
         progress.set_style(
             ProgressStyle::default_bar()
                 .template("[2/2] Calculating scores: {bar:40.cyan/blue} {msg} ~{eta} remaining")
                 .progress_chars("█▓▒░  "),
         );
+        progress.set_position(entries.len() as u64);
 
         let mut running = true;
+        let opening_database = self.opening_database.clone();
         thread::spawn(move || {
             positions.par_iter().for_each_with(
                 tx.clone(),
                 |tx, (huffman_code, player_mask, board_mask)| {
-                    let board = BitBoard::from_parts(*player_mask, *board_mask, 12);
+                    let board = BitBoard::from_parts(*player_mask, *board_mask, depth);
 
                     let mut solver = Solver::new(board);
+                    if let Some(database) = opening_database.clone() {
+                        solver = solver.with_opening_database(database);
+                    }
                     let (score, _) = solver.solve();
 
                     tx.send(Message2::Value((*huffman_code, score as i8)))
@@ -238,17 +716,24 @@ impl OpeningDatabase {
             tx.send(Message2::Finish).unwrap();
         });
 
-        let mut entries = Vec::new();
         let mut delta = 0;
         while running {
             match rx.recv()? {
                 Message2::Finish => running = false,
                 Message2::Value(entry) => {
+                    if let Some(file) = checkpoint_file.as_mut() {
+                        file.write_u32::<BigEndian>(entry.0)?;
+                        file.write_i8(entry.1)?;
+                    }
                     entries.push(entry);
                     delta += 1;
                 }
             }
             if Instant::now() > next_time {
+                // a crash can only lose the scores calculated since the last flush
+                if let Some(file) = checkpoint_file.as_mut() {
+                    file.flush()?;
+                }
                 progress.inc(delta);
                 delta = 0;
                 progress.set_message(&format!(
@@ -260,100 +745,142 @@ impl OpeningDatabase {
             }
         }
 
+        if let Some(file) = checkpoint_file.as_mut() {
+            file.flush()?;
+        }
         progress.finish();
+        entries.sort_unstable();
+        Ok(entries)
+    }
+}
 
-        print!(
-            "Calculations complete, writing out to {} ... ",
-            DATABASE_PATH
-        );
+/// Writes a generated position in the temp file format
+fn write_position(writer: &mut impl Write, position: Position) -> Result<()> {
+    writer.write_u32::<BigEndian>(position.0)?;
+    writer.write_u64::<BigEndian>(position.1)?;
+    writer.write_u64::<BigEndian>(position.2)?;
+    Ok(())
+}
 
-        entries.sort_unstable();
+/// Reads a generated position in the temp file format
+fn read_position(reader: &mut impl Read) -> Result<Position> {
+    Ok((
+        reader.read_u32::<BigEndian>()?,
+        reader.read_u64::<BigEndian>()?,
+        reader.read_u64::<BigEndian>()?,
+    ))
+}
 
-        let mut file = BufWriter::new(
-            OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(DATABASE_PATH)?,
-        );
+impl Default for DatabaseGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        for entry in entries {
-            file.write_u32::<BigEndian>(entry.0)?;
-            file.write_i8(entry.1)?;
-        }
-        println!("Complete");
+struct OpeningDatabaseStorage {
+    depth: usize,
+    entries: DatabaseEntries,
+}
 
-        let finish = Instant::now();
-        println!(
-            "Opening database generation completed in {}",
-            HumanDuration(finish - start)
-        );
+enum DatabaseEntries {
+    /// Entries read into memory
+    InMemory {
+        positions: Vec<u32>,
+        values: Vec<i8>,
+    },
+    /// Entries searched directly in a memory-mapped database file, starting at `offset`
+    Mapped { mapping: Mmap, offset: usize },
+}
 
-        Ok(())
+/// Returns the depth of a database file and the offset of its first entry, treating files
+/// without a header as databases at the hard-coded depth
+fn parse_header(bytes: &[u8]) -> (usize, usize) {
+    match bytes {
+        [m0, m1, m2, m3, depth, ..] if [*m0, *m1, *m2, *m3] == DATABASE_MAGIC => {
+            (*depth as usize, ENTRY_SIZE)
+        }
+        _ => (DATABASE_DEPTH, 0),
     }
 }
 
-#[derive(Clone)]
-struct OpeningDatabaseStorage {
-    positions: Vec<u32>,
-    values: Vec<i8>,
+/// Checks that position codes are strictly ascending, as binary search requires
+fn check_order(positions: impl Iterator<Item = u32>) -> Result<()> {
+    let mut previous = None;
+    for (i, position) in positions.enumerate() {
+        if let Some(previous) = previous.filter(|&previous| previous >= position) {
+            return Err(anyhow!(
+                "Invalid database, entry {} ({:#010x}) is not greater than the previous entry ({:#010x})",
+                i,
+                position,
+                previous
+            ));
+        }
+        previous = Some(position);
+    }
+    Ok(())
 }
 
 impl OpeningDatabaseStorage {
-    pub fn load() -> Result<Self> {
-        let mut file = BufReader::new(File::open(DATABASE_PATH)?);
-        let mut positions = vec![0; DATABASE_NUM_POSITIONS];
-        let mut values = vec![0; DATABASE_NUM_POSITIONS];
-
-        for i in 0..DATABASE_NUM_POSITIONS {
-            // read encoded position and winner
-            let mut bytes = [0; 4];
-            file.read_exact(&mut bytes)?;
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let (depth, offset) = parse_header(&bytes);
+        // the number of positions is derived from the length of the file
+        let (entries, _) = bytes[offset..].as_chunks::<ENTRY_SIZE>();
+        let positions: Vec<u32> = entries
+            .iter()
+            .map(|entry| u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]))
+            .collect();
+        let values = entries.iter().map(|entry| entry[4] as i8).collect();
+
+        check_order(positions.iter().copied())?;
+        Ok(Self {
+            depth,
+            entries: DatabaseEntries::InMemory { positions, values },
+        })
+    }
 
-            positions[i] = u32::from_be_bytes(bytes);
+    pub fn map(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only, and the file must not be modified while it is mapped
+        // (see `OpeningDatabase::map_from`)
+        let mapping = unsafe { Mmap::map(&file)? };
+        let (depth, offset) = parse_header(&mapping);
+        // the entries are checked once here so that lookups can trust the order
+        let (entries, _) = mapping[offset..].as_chunks::<ENTRY_SIZE>();
+        check_order(
+            entries
+                .iter()
+                .map(|entry| u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]])),
+        )?;
+        Ok(Self {
+            depth,
+            entries: DatabaseEntries::Mapped { mapping, offset },
+        })
+    }
 
-            let mut byte = [0];
-            file.read_exact(&mut byte)?;
-            values[i] = i8::from_be_bytes(byte);
+    pub fn len(&self) -> usize {
+        match &self.entries {
+            DatabaseEntries::InMemory { positions, .. } => positions.len(),
+            DatabaseEntries::Mapped { mapping, offset } => (mapping.len() - offset) / ENTRY_SIZE,
         }
-        Ok(Self { positions, values })
     }
 
     pub fn get(&self, position_code: u32) -> Option<i32> {
-        // variables for binary search state
-        let mut step = DATABASE_NUM_POSITIONS - 1;
-        let mut pos1 = step;
-
-        // invalid value
-        let mut value = -99;
-
-        // Binary search
-        while step > 0 {
-            // divide step by 2, always rounding up apart from at 0.5
-            step = if step != 1 {
-                (step + (step & 1)) >> 1
-            } else {
-                0
-            };
-
-            // only one of the position code and its mirror will be present,
-            // so one of these indices can become invalid
-            let code1 = *self.positions.get(pos1).unwrap_or(&0);
-
-            match position_code.cmp(&code1) {
-                // overflow is acceptable as the Vec::get earlier guards against panic
-                Ordering::Less => pos1 = pos1.wrapping_sub(step),
-                Ordering::Greater => pos1 = pos1.wrapping_add(step),
-                Ordering::Equal => {
-                    value = self.values[pos1];
-                    break;
-                }
+        // positions are sorted in ascending order, the database size is arbitrary
+        match &self.entries {
+            DatabaseEntries::InMemory { positions, values } => positions
+                .binary_search(&position_code)
+                .ok()
+                .map(|i| values[i] as i32),
+            DatabaseEntries::Mapped { mapping, offset } => {
+                let (entries, _) = mapping[*offset..].as_chunks::<ENTRY_SIZE>();
+                entries
+                    .binary_search_by_key(&position_code, |entry| {
+                        u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]])
+                    })
+                    .ok()
+                    .map(|i| entries[i][4] as i8 as i32)
             }
         }
-        if value != -99 {
-            Some(value as i32)
-        } else {
-            None
-        }
     }
 }
@@ -1,25 +1,71 @@
 //! An agent to solve the game of Connect 4
 
-use crate::{bitboard::*, opening_database::*, transposition_table::*, HEIGHT, WIDTH};
+#[cfg(feature = "native")]
+use crate::opening_database::*;
+use crate::{bitboard::*, transposition_table::*, HEIGHT, WIDTH};
 
-use std::cmp::Ordering;
+use alloc::{format, string::String, vec::Vec};
+use anyhow::Result;
+use core::cmp::Ordering;
+#[cfg(feature = "native")]
+use core::sync::atomic::{self, AtomicBool, AtomicUsize};
+use core::time::Duration;
+#[cfg(feature = "native")]
+use rayon::prelude::*;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// The minimum possible score of a position
-pub const MIN_SCORE: i32 = -((WIDTH * HEIGHT) as i32) / 2 + 3;
+pub const MIN_SCORE: i32 = Solver::MIN_SCORE;
 /// The maximum possible score of a postion
-pub const MAX_SCORE: i32 = ((WIDTH * HEIGHT) as i32 + 1) / 2 - 3;
+pub const MAX_SCORE: i32 = Solver::MAX_SCORE;
 
-struct MoveSorter {
+/// A time to stop a search at, which needs the clock from `std`
+#[cfg(feature = "std")]
+type Deadline = Instant;
+/// A time to stop a search at, which can't be created without the clock from `std`
+#[cfg(not(feature = "std"))]
+type Deadline = core::convert::Infallible;
+
+#[cfg(feature = "std")]
+fn deadline_passed(deadline: Deadline) -> bool {
+    Instant::now() >= deadline
+}
+
+#[cfg(not(feature = "std"))]
+fn deadline_passed(deadline: Deadline) -> bool {
+    match deadline {}
+}
+
+/// The last move to cause a beta cutoff after each number of moves, as the column plus one so that
+/// 0 is no move, which fits every board size as `W * H` is less than 64
+type Killers = [u8; 64];
+
+/// The heuristic score of winning, higher than any evaluation of an undecided position
+const HEURISTIC_WIN_SCORE: i32 = 10_000;
+/// The lowest heuristic score of a decided win, as no board has more than 64 squares. Estimates
+/// of undecided positions are kept strictly between this and its negation
+const HEURISTIC_DECIDED_SCORE: i32 = HEURISTIC_WIN_SCORE - 64;
+
+/// The difficulty level of [`Solver::solve_with_difficulty`] that always plays the best move
+///
+/// [`Solver::solve_with_difficulty`]: struct.GenericSolver.html#method.solve_with_difficulty
+pub const MAX_DIFFICULTY: u8 = 10;
+
+/// The default seed of the random number generator used for suboptimal play
+const DEFAULT_RANDOM_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+struct MoveSorter<const W: usize> {
     size: usize,
     // move bitmap, column and score
-    moves: [(u64, usize, i32); WIDTH],
+    moves: [(u64, usize, i32); W],
 }
 
-impl MoveSorter {
+impl<const W: usize> MoveSorter<W> {
     pub fn new() -> Self {
         Self {
             size: 0,
-            moves: [(0, 0, 0); WIDTH],
+            moves: [(0, 0, 0); W],
         }
     }
     pub fn push(&mut self, new_move: u64, column: usize, score: i32) {
@@ -32,7 +78,7 @@ impl MoveSorter {
         self.moves[pos] = (new_move, column, score);
     }
 }
-impl Iterator for MoveSorter {
+impl<const W: usize> Iterator for MoveSorter<W> {
     type Item = (u64, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -46,18 +92,188 @@ impl Iterator for MoveSorter {
     }
 }
 
+/// Callbacks made by the game tree search
+trait SearchHook {
+    /// Called once for every node searched
+    fn visit_node(&mut self);
+}
+
+impl SearchHook for () {
+    #[inline(always)]
+    fn visit_node(&mut self) {}
+}
+
+/// Calls a function after a fixed number of nodes have been searched
+struct Yielder<F: FnMut()> {
+    yield_every: usize,
+    remaining: usize,
+    yield_fn: F,
+}
+
+impl<F: FnMut()> SearchHook for Yielder<F> {
+    #[inline(always)]
+    fn visit_node(&mut self) {
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.remaining = self.yield_every;
+            (self.yield_fn)();
+        }
+    }
+}
+
 /// Returns a slice ordering the columns from the middle outwards, as
 /// the middle columns are often better moves
 pub const fn move_order() -> [usize; WIDTH] {
-    let mut move_order = [0; WIDTH];
+    column_order::<WIDTH>()
+}
+
+/// Returns the columns of a board `W` columns wide, ordered from the middle outwards
+pub(crate) const fn column_order<const W: usize>() -> [usize; W] {
+    let mut move_order = [0; W];
     let mut i = 0;
-    while i < WIDTH {
-        move_order[i] = (WIDTH / 2) + (i % 2) * (i / 2 + 1) - (1 - i % 2) * (i / 2);
+    while i < W {
+        // even widths start from the left of the two middle columns
+        move_order[i] = (W - 1) / 2 + (i % 2) * (i / 2 + 1) - (1 - i % 2) * (i / 2);
         i += 1;
     }
     move_order
 }
 
+/// Statistics describing how difficult a position is to play correctly (see [`Solver::complexity`])
+///
+/// [`Solver::complexity`]: struct.GenericSolver.html#method.complexity
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Complexity {
+    /// The number of nodes searched to score every legal move
+    pub node_count: usize,
+    /// The score difference between the best and second best moves, or `None` if there is only one legal move
+    pub best_move_margin: Option<i32>,
+    /// The number of moves that don't give the opponent an immediate win
+    pub num_non_losing_moves: usize,
+}
+
+/// The reason for a move chosen by [`Solver::explain`], from the most to the least specific
+///
+/// [`Solver::explain`]: struct.GenericSolver.html#method.explain
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MoveReason {
+    /// The move completes an alignment
+    ImmediateWin,
+    /// The move blocks a square where the opponent would complete an alignment
+    ForcedBlock,
+    /// The move leaves the opponent unable to stop an immediate win, for example by creating two
+    /// threats at once
+    DoubleThreat,
+    /// Every other move gives the opponent an immediate win
+    OnlyNonLosingMove,
+    /// None of the above, the move keeps the best score available
+    Positional,
+}
+
+impl MoveReason {
+    /// Returns a short human-readable description of the reason
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::ImmediateWin => "immediate win",
+            Self::ForcedBlock => "blocks opponent's immediate win (forced)",
+            Self::DoubleThreat => "creates double threat",
+            Self::OnlyNonLosingMove => "only non-losing move",
+            Self::Positional => "maintains advantage / even game",
+        }
+    }
+}
+
+/// The best move in a position, with the reason it was chosen (see [`Solver::explain`])
+///
+/// [`Solver::explain`]: struct.GenericSolver.html#method.explain
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MoveExplanation {
+    /// The column of the best move
+    pub column: usize,
+    /// The score of the position (see [Position Scoring](struct.GenericSolver.html#position-scoring))
+    pub score: i32,
+    /// The reason the move was chosen
+    pub reason: MoveReason,
+}
+
+/// The result of a search with statistics about it (see [`Solver::solve_with_stats`])
+///
+/// [`Solver::solve_with_stats`]: struct.GenericSolver.html#method.solve_with_stats
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SearchStats {
+    /// The score of the position (see [Position Scoring](struct.GenericSolver.html#position-scoring))
+    pub score: i32,
+    /// The column of the best move
+    pub best_move: usize,
+    /// The number of nodes searched
+    pub nodes: usize,
+    /// The wall-clock time taken by the search, always zero without the `native` feature as
+    /// targets like `wasm32-unknown-unknown` have no clock
+    pub elapsed: Duration,
+    /// The search depth reached, as logged by
+    /// [`Solver::solve_verbose`](struct.GenericSolver.html#method.solve_verbose)
+    pub depth_reached: usize,
+}
+
+/// The progress of an iterative deepening search, at the start of each iteration (see
+/// [`Solver::solve_with_progress`])
+///
+/// [`Solver::solve_with_progress`]: struct.GenericSolver.html#method.solve_with_progress
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SearchProgress {
+    /// The search depth reached, as logged by
+    /// [`Solver::solve_verbose`](struct.GenericSolver.html#method.solve_verbose)
+    pub depth: usize,
+    /// The lowest score the position can still have
+    pub window_low: i32,
+    /// The highest score the position can still have
+    pub window_high: i32,
+    /// The number of nodes searched so far
+    pub nodes: usize,
+}
+
+/// Whether the player to move can force a win, and how soon (see [`Solver::outcome`])
+///
+/// [`Solver::outcome`]: struct.GenericSolver.html#method.outcome
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    /// `Greater` if the player to move can force a win, `Less` if their opponent can and
+    /// `Equal` for a draw
+    pub result: Ordering,
+    /// The number of moves the winner needs to win, counting the winning move, or the number of
+    /// empty squares for a draw (see [`Solver::score_to_win_distance`])
+    ///
+    /// [`Solver::score_to_win_distance`]: struct.GenericSolver.html#method.score_to_win_distance
+    pub in_moves: usize,
+    /// The column of the best move
+    pub best_move: usize,
+}
+
+impl Outcome {
+    /// Returns a human-readable description of the outcome, given the player to move
+    pub fn description(&self, player: Player) -> String {
+        let (player_to_move, opponent) = match player {
+            Player::One => (1, 2),
+            Player::Two => (2, 1),
+        };
+        let move_string = if self.in_moves == 1 { "move" } else { "moves" };
+        match self.result {
+            Ordering::Greater => format!(
+                "Player {} can force a win in at most {} {}.",
+                player_to_move, self.in_moves, move_string
+            ),
+            Ordering::Less => format!(
+                "Player {} can force a win in at most {} {}.",
+                opponent, self.in_moves, move_string
+            ),
+            Ordering::Equal => format!(
+                "Player {} can at best force a draw, {} {} remaining",
+                player_to_move, self.in_moves, move_string
+            ),
+        }
+    }
+}
+
 /// An agent to solve Connect 4 positions
 ///
 /// # Notes
@@ -69,93 +285,523 @@ pub const fn move_order() -> [usize; WIDTH] {
 /// If the first player wins with their final placed tile (their 21st tile in a 7x6 board)
 /// the score is 1, or -1 if the the second player wins with their final tile. Earlier wins
 /// have scores further from 0, up to 18/-18, where a player wins with their 4th tile. A drawn position
-/// has a score of 0
+/// has a score of 0. Other board sizes are scored the same way, between [`MIN_SCORE`] and
+/// [`MAX_SCORE`] for that size
+///
+/// [`MIN_SCORE`]: #associatedconstant.MIN_SCORE
+/// [`MAX_SCORE`]: #associatedconstant.MAX_SCORE
 #[derive(Clone)]
-pub struct Solver {
-    board: BitBoard,
+pub struct GenericSolver<const W: usize, const H: usize, T = TranspositionTable> {
+    board: GenericBitBoard<W, H>,
     
     /// The number of nodes searched by this `Solver` so far (for diagnostics only)
     pub node_count: usize,
-    transposition_table: TranspositionTable,
+    transposition_table: T,
+    #[cfg(feature = "native")]
     opening_database: Option<OpeningDatabase>,
+    prefer_slow_wins: bool,
+    fast_move_ordering: bool,
+    prune_symmetric_moves: bool,
+    canonical_table_keys: bool,
+    killer_moves: bool,
+    // the score window of the last iterative deepening search, or `None` before searching
+    bounds: Option<(i32, i32)>,
+    // the state of the random number generator for suboptimal play
+    random_state: u64,
+    // the time limit and number of threads `solve` searches with
+    #[cfg(feature = "native")]
+    timeout: Option<Duration>,
+    #[cfg(feature = "native")]
+    threads: Option<usize>,
 }
 
-impl Solver {
+/// A `Solver` for the standard 7x6 board (see [`GenericSolver`])
+///
+/// [`GenericSolver`]: struct.GenericSolver.html
+pub type Solver = GenericSolver<WIDTH, HEIGHT>;
 
-    /// Creates a new `Solver` from a bitboard
-    pub fn new(board: BitBoard) -> Self {
+/// A builder to configure a [`GenericSolver`] in one place before creating it
+///
+/// # Notes
+/// Options that aren't set keep the defaults of [`GenericSolver::new`]. The search options of
+/// the built `Solver`, like [`prefer_slow_wins`](GenericSolver::prefer_slow_wins), are still
+/// configured on the `Solver` itself
+///
+/// # Example
+/// ```
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use connect4_ai::{
+///     bitboard::BitBoard, solver::SolverBuilder, transposition_table::SharedTranspositionTable,
+/// };
+///
+/// let mut solver = SolverBuilder::new()
+///     .transposition_table(SharedTranspositionTable::new())
+///     .threads(2)
+///     .build(BitBoard::from_moves("112233")?);
+///
+/// // the first player wins with their 4th tile
+/// assert_eq!(solver.solve(), (18, 3));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`GenericSolver`]: struct.GenericSolver.html
+/// [`GenericSolver::new`]: struct.GenericSolver.html#method.new
+pub struct GenericSolverBuilder<const W: usize, const H: usize, T = TranspositionTable> {
+    transposition_table: Option<T>,
+    #[cfg(feature = "native")]
+    opening_database: Option<OpeningDatabase>,
+    #[cfg(feature = "native")]
+    timeout: Option<Duration>,
+    #[cfg(feature = "native")]
+    threads: Option<usize>,
+}
+
+/// A `SolverBuilder` for the standard 7x6 board (see [`GenericSolverBuilder`])
+///
+/// [`GenericSolverBuilder`]: struct.GenericSolverBuilder.html
+pub type SolverBuilder = GenericSolverBuilder<WIDTH, HEIGHT>;
+
+impl<const W: usize, const H: usize> GenericSolverBuilder<W, H> {
+    /// Creates a builder with the default configuration
+    pub fn new() -> Self {
         Self {
-            board,
-            node_count: 0,
-            transposition_table: TranspositionTable::new(),
+            transposition_table: None,
+            #[cfg(feature = "native")]
             opening_database: None,
+            #[cfg(feature = "native")]
+            timeout: None,
+            #[cfg(feature = "native")]
+            threads: None,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for GenericSolverBuilder<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize, T: TranspositionStore> GenericSolverBuilder<W, H, T> {
+    /// Sets the transposition table of the `Solver`, which can be shared with other `Solver`s
+    /// by cloning a [`SharedTranspositionTable`]. A new table is allocated if this isn't set
+    ///
+    /// [`SharedTranspositionTable`]: ../transposition_table/struct.SharedTranspositionTable.html
+    pub fn transposition_table<U: TranspositionStore>(
+        self,
+        transposition_table: U,
+    ) -> GenericSolverBuilder<W, H, U> {
+        GenericSolverBuilder {
+            transposition_table: Some(transposition_table),
+            #[cfg(feature = "native")]
+            opening_database: self.opening_database,
+            #[cfg(feature = "native")]
+            timeout: self.timeout,
+            #[cfg(feature = "native")]
+            threads: self.threads,
         }
     }
 
+    /// Sets a time limit for [`solve_timed`](GenericSolver::solve_timed), after which the best
+    /// move found so far is returned with bounds on the score (see
+    /// [`solve_with_timeout`](GenericSolver::solve_with_timeout)). [`solve`](GenericSolver::solve)
+    /// still searches until the score is exact
+    #[cfg(feature = "native")]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Creates a `Solver` for `board` with the configured options
+    pub fn build(self, board: GenericBitBoard<W, H>) -> GenericSolver<W, H, T>
+    where
+        T: Default,
+    {
+        let solver = GenericSolver::new_with_transposition_table(
+            board,
+            self.transposition_table.unwrap_or_default(),
+        );
+        #[cfg(feature = "native")]
+        let solver = GenericSolver {
+            opening_database: self.opening_database,
+            timeout: self.timeout,
+            threads: self.threads,
+            ..solver
+        };
+        solver
+    }
+}
+
+/// Methods that search on several threads, which share the transposition table
+#[cfg(feature = "native")]
+impl<const W: usize, const H: usize> GenericSolverBuilder<W, H, SharedTranspositionTable> {
+    /// Sets the number of threads [`solve`](GenericSolver::solve) searches on (see
+    /// [`solve_parallel`](GenericSolver::solve_parallel)), where 0 uses one thread per CPU
+    ///
+    /// The threads share the transposition table, so this needs a [`SharedTranspositionTable`]
+    /// to be set first
+    ///
+    /// [`SharedTranspositionTable`]: ../transposition_table/struct.SharedTranspositionTable.html
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+}
+
+/// Methods that rely on the opening database of the standard board size
+impl<T: TranspositionStore> GenericSolverBuilder<WIDTH, HEIGHT, T> {
+    /// Sets the opening database of the `Solver` (see [`GenericSolver::with_opening_database`])
+    ///
+    /// [`GenericSolver::with_opening_database`]: struct.GenericSolver.html#method.with_opening_database
+    #[cfg(feature = "native")]
+    pub fn opening_database(mut self, opening_database: OpeningDatabase) -> Self {
+        self.opening_database = Some(opening_database);
+        self
+    }
+}
+
+impl<const W: usize, const H: usize> GenericSolver<W, H> {
+    /// Creates a new `Solver` from a bitboard
+    pub fn new(board: GenericBitBoard<W, H>) -> Self {
+        GenericSolverBuilder::new().build(board)
+    }
+
+    /// Creates a new `Solver` from a bitboard with a thread-safe transposition table, so that
+    /// `Solver`s on different threads can share their results
+    pub fn new_with_shared_table(
+        board: GenericBitBoard<W, H>,
+        table: SharedTranspositionTable,
+    ) -> GenericSolver<W, H, SharedTranspositionTable> {
+        GenericSolverBuilder::new()
+            .transposition_table(table)
+            .build(board)
+    }
+
+    /// Creates a new `Solver` from a string of 1-indexed moves (see [`BitBoard::from_moves`])
+    ///
+    /// [`BitBoard::from_moves`]: ../bitboard/struct.GenericBitBoard.html#method.from_moves
+    pub fn from_moves<S: AsRef<str>>(moves: S) -> Result<Self> {
+        Ok(Self::new(GenericBitBoard::<W, H>::from_moves(moves)?))
+    }
+
+    /// Creates a new `Solver` from a slice of 0-indexed moves (see [`BitBoard::from_slice`])
+    ///
+    /// [`BitBoard::from_slice`]: ../bitboard/struct.GenericBitBoard.html#method.from_slice
+    pub fn from_slice(moves: &[usize]) -> Result<Self, BoardError> {
+        Ok(Self::new(GenericBitBoard::<W, H>::from_slice(moves)?))
+    }
+}
+
+impl<const W: usize, const H: usize, T: TranspositionStore> GenericSolver<W, H, T> {
+    /// The minimum possible score of a position on this board size
+    pub const MIN_SCORE: i32 = -((W * H) as i32) / 2 + 3;
+    /// The maximum possible score of a position on this board size
+    pub const MAX_SCORE: i32 = ((W * H) as i32 + 1) / 2 - 3;
+
     /// Creates a new `Solver` from a bitboard with a given transposition table
+    ///
+    /// # Panics
+    /// Panics if the table is too small to tell apart every key of this board size (see
+    /// [`TranspositionTable::with_capacity`])
+    ///
+    /// [`TranspositionTable::with_capacity`]: ../transposition_table/struct.TranspositionTable.html#method.with_capacity
     pub fn new_with_transposition_table(
-        board: BitBoard,
-        transposition_table: TranspositionTable,
+        board: GenericBitBoard<W, H>,
+        transposition_table: T,
     ) -> Self {
+        assert!(
+            transposition_table.stores_keys_of(W * (H + 1)),
+            "the transposition table is too small for the keys of this board size"
+        );
         Self {
             board,
             node_count: 0,
             transposition_table,
+            #[cfg(feature = "native")]
             opening_database: None,
+            prefer_slow_wins: false,
+            fast_move_ordering: false,
+            prune_symmetric_moves: false,
+            canonical_table_keys: false,
+            killer_moves: false,
+            bounds: None,
+            random_state: DEFAULT_RANDOM_SEED,
+            #[cfg(feature = "native")]
+            timeout: None,
+            #[cfg(feature = "native")]
+            threads: None,
         }
     }
 
-    /// Adds an opening database to an existing `Solver`
-    pub fn with_opening_database(mut self, opening_database: OpeningDatabase) -> Self {
-        self.opening_database = Some(opening_database);
+    /// Replaces the position being solved, keeping the transposition table, opening database
+    /// and configuration, and resets the node count and [search bounds](Self::current_bounds)
+    ///
+    /// # Notes
+    /// The transposition table is keyed by position, so its entries stay valid for any board.
+    /// This lets a game loop keep one `Solver` and reuse the results of earlier searches
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::{bitboard::BitBoard, solver::Solver};
+    ///
+    /// let mut solver = Solver::new(BitBoard::from_moves("4455443")?);
+    /// let (_, best_move) = solver.solve();
+    ///
+    /// let mut board = BitBoard::from_moves("4455443")?;
+    /// board.play_column(best_move)?;
+    /// solver.set_board(board);
+    /// assert_eq!((solver.num_moves(), solver.node_count), (8, 0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_board(&mut self, board: GenericBitBoard<W, H>) {
+        self.board = board;
+        self.node_count = 0;
+        self.bounds = None;
+    }
+
+    /// Returns the lowest and highest scores the position can have, as narrowed down by the last
+    /// iterative deepening search
+    ///
+    /// # Notes
+    /// `min == max` means the search finished and the score is exact. A search cut off early,
+    /// like [`solve_with_timeout`](Self::solve_with_timeout), leaves a wider window, so an
+    /// interface can show a range of scores instead of a misleadingly precise one. Before any
+    /// search this is the full range of scores for the position
+    pub fn current_bounds(&self) -> (i32, i32) {
+        self.bounds.unwrap_or_else(|| self.score_window())
+    }
+
+    /// Returns the lowest and highest scores possible with the number of moves left
+    fn score_window(&self) -> (i32, i32) {
+        (
+            -(((W * H) as i32) - self.board.num_moves() as i32) / 2,
+            (W * H + 1 - self.board.num_moves()) as i32 / 2,
+        )
+    }
+
+    /// Returns whether an opening database is attached to this `Solver`
+    pub fn has_opening_database(&self) -> bool {
+        self.database_depth().is_some()
+    }
+
+    /// Returns the number of tiles in the positions of the attached opening database, or `None`
+    /// if no database is attached
+    pub fn database_depth(&self) -> Option<usize> {
+        #[cfg(feature = "native")]
+        return self.opening_database.as_ref().map(OpeningDatabase::depth);
+        #[cfg(not(feature = "native"))]
+        None
+    }
+
+    /// Configures an existing `Solver` to choose the slowest forced win instead of the fastest
+    ///
+    /// Only the selected move is affected, the reported score is still exact. This is useful for
+    /// prolonging games against weaker human players
+    pub fn prefer_slow_wins(mut self, prefer_slow_wins: bool) -> Self {
+        self.prefer_slow_wins = prefer_slow_wins;
+        self
+    }
+
+    /// Configures an existing `Solver` to order moves by distance from the centre column instead
+    /// of by the threats they create
+    ///
+    /// Each node is cheaper to search but the worse ordering searches more nodes, which can be
+    /// faster overall for easy positions
+    pub fn fast_move_ordering(mut self, fast_move_ordering: bool) -> Self {
+        self.fast_move_ordering = fast_move_ordering;
+        self
+    }
+
+    /// Configures an existing `Solver` to skip moves that mirror another move in symmetric
+    /// positions (see [`BitBoard::is_symmetric`])
+    ///
+    /// The mirrored moves lead to mirrored positions with the same score, so exact scores are
+    /// unchanged. Symmetric positions are rare after the opening, so this mostly helps when
+    /// solving early positions without an opening database. The moves of the position being
+    /// solved are always pruned this way, this extends it to the rest of the tree
+    ///
+    /// [`BitBoard::is_symmetric`]: ../bitboard/struct.GenericBitBoard.html#method.is_symmetric
+    pub fn prune_symmetric_moves(mut self, prune_symmetric_moves: bool) -> Self {
+        self.prune_symmetric_moves = prune_symmetric_moves;
+        self
+    }
+
+    /// Configures an existing `Solver` to store a position and its mirror image under the same
+    /// transposition table key (see [`BitBoard::canonical`])
+    ///
+    /// Mirrored positions have the same score, so the stored bounds are valid for both and
+    /// exact scores are unchanged. The table only stores score bounds, never moves, so the best
+    /// move is still found by searching from the actual position
+    ///
+    /// [`BitBoard::canonical`]: ../bitboard/struct.GenericBitBoard.html#method.canonical
+    pub fn canonical_table_keys(mut self, canonical_table_keys: bool) -> Self {
+        self.canonical_table_keys = canonical_table_keys;
         self
     }
 
+    /// Configures an existing `Solver` to search killer moves first, the last moves to cause a
+    /// beta cutoff at the same depth of the search, among moves with the same ordering score
+    ///
+    /// A move that refutes one position often refutes its siblings too, so trying it first
+    /// prunes more of the tree. Only the order moves are searched in changes, so exact scores are
+    /// unchanged. Killer moves only break ties between moves with the same ordering score.
+    ///
+    /// This only has an effect together with [`fast_move_ordering`](Self::fast_move_ordering),
+    /// as the threat-based move ordering finds the refutation more often than the killer move:
+    /// solving all of `Test_L1_R3` with the opening database searches 177,876,482 nodes instead
+    /// of 195,389,841 with the fast move ordering, but would search 107,088,878 instead of
+    /// 56,143,865 with the default ordering
+    pub fn killer_moves(mut self, killer_moves: bool) -> Self {
+        self.killer_moves = killer_moves;
+        self
+    }
+
+    /// Configures an existing `Solver` to seed the random number generator used by
+    /// [`solve_with_difficulty`](Self::solve_with_difficulty)
+    ///
+    /// The same seed always chooses the same sequence of moves, for reproducible games
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_state = seed;
+        self
+    }
+
+    /// Returns a copy of this `Solver` using a different transposition table
+    #[cfg(feature = "native")]
+    fn with_transposition_table<U>(&self, transposition_table: U) -> GenericSolver<W, H, U> {
+        GenericSolver {
+            board: self.board,
+            node_count: self.node_count,
+            transposition_table,
+            opening_database: self.opening_database.clone(),
+            prefer_slow_wins: self.prefer_slow_wins,
+            fast_move_ordering: self.fast_move_ordering,
+            prune_symmetric_moves: self.prune_symmetric_moves,
+            canonical_table_keys: self.canonical_table_keys,
+            killer_moves: self.killer_moves,
+            bounds: self.bounds,
+            random_state: self.random_state,
+            timeout: self.timeout,
+            threads: self.threads,
+        }
+    }
+
+    /// Returns the transposition table key of a board, which is the same for the board and its
+    /// mirror image if canonical keys are enabled
+    fn table_key(&self, board: &GenericBitBoard<W, H>) -> u64 {
+        if self.canonical_table_keys {
+            board.canonical_key()
+        } else {
+            board.key()
+        }
+    }
+
+    /// Returns the moves from `non_losing_moves` to search, skipping the right half of the
+    /// board in symmetric positions if enabled
+    fn moves_to_search(&self, non_losing_moves: u64) -> u64 {
+        if self.prune_symmetric_moves {
+            self.root_moves_to_search(non_losing_moves)
+        } else {
+            non_losing_moves
+        }
+    }
+
+    /// Returns the moves from `non_losing_moves` to search at the root, always skipping the
+    /// right half of the board in symmetric positions
+    ///
+    /// Each skipped move has the same score as its mirror on the left, so the best move found is
+    /// the lower-indexed column of a symmetric pair
+    fn root_moves_to_search(&self, non_losing_moves: u64) -> u64 {
+        if self.board.is_symmetric() {
+            // moves right of the centre mirror the moves left of it
+            non_losing_moves & !(!0 << (((W - 1) / 2 + 1) * (H + 1)))
+        } else {
+            non_losing_moves
+        }
+    }
+
+    /// Returns whether killer moves are used to order moves
+    fn uses_killer_moves(&self) -> bool {
+        self.killer_moves && self.fast_move_ordering
+    }
+
+    /// Scores a candidate move for move ordering given the killer move for the position, higher
+    /// scores are searched first
+    fn order_score(&self, candidate: u64, column: usize, killer: u8) -> i32 {
+        let score = if self.fast_move_ordering {
+            // twice the distance from the centre, which is between two columns for even widths
+            -(2 * column as i32 - (W - 1) as i32).abs()
+        } else {
+            self.board.move_score(candidate)
+        };
+        if self.uses_killer_moves() {
+            // break ties in favour of the killer move
+            let killer = killer as usize == column + 1;
+            2 * score + killer as i32
+        } else {
+            score
+        }
+    }
+
     /// Performs game tree search
     ///
     /// Returns the score of the position (see [Position Scoring])
     ///
     /// [Position Scoring]: #position-scoring
-    fn negamax(&mut self, mut alpha: i32, mut beta: i32) -> i32 {
+    fn negamax<Hook: SearchHook>(
+        &mut self,
+        mut alpha: i32,
+        mut beta: i32,
+        hook: &mut Hook,
+        killers: &mut Killers,
+    ) -> i32 {
         self.node_count += 1;
+        hook.visit_node();
 
         // check for next-move win for current player
-        for column in 0..WIDTH {
+        for column in 0..W {
             if self.board.playable(column) && self.board.check_winning_move(column) {
-                return ((WIDTH * HEIGHT + 1 - self.board.num_moves()) / 2) as i32;
+                return ((W * H + 1 - self.board.num_moves()) / 2) as i32;
             }
         }
 
         // look for moves that don't give the opponent a next turn win
         let non_losing_moves = self.board.non_losing_moves();
         if non_losing_moves == 0 {
-            return -((WIDTH * HEIGHT) as i32 - self.board.num_moves() as i32) / 2;
+            return -((W * H) as i32 - self.board.num_moves() as i32) / 2;
         }
 
         // check for draw
-        if self.board.num_moves() == WIDTH * HEIGHT {
+        if self.board.is_full() {
             return 0;
         }
 
         // check opening table at appropriate depth
-        if self.board.num_moves() == DATABASE_DEPTH {
-            if let Some(database) = &self.opening_database {
-                if let Some(score) = database.get(self.board.huffman_code()) {
+        #[cfg(feature = "native")]
+        if let Some(database) = &self.opening_database {
+            if self.board.num_moves() == database.depth() {
+                if let Some(score) =
+                    database.get(self.board.huffman_code(), self.board.huffman_code_mirror())
+                {
                     return score;
                 }
             }
         }
 
         // upper bound of score
-        let mut max = (((WIDTH * HEIGHT) - 1 - self.board.num_moves()) / 2) as i32;
+        let mut max = (((W * H) - 1 - self.board.num_moves()) / 2) as i32;
 
         // try to fetch the upper/lower bound of the score from the transposition table
-        let key = self.board.key();
+        let key = self.table_key(&self.board);
         let value = self.transposition_table.get(key) as i32;
         if value != 0 {
             // check if lower bound
-            if value > MAX_SCORE - MIN_SCORE + 1 {
-                let min = value + 2 * MIN_SCORE - MAX_SCORE - 2;
+            if value > Self::MAX_SCORE - Self::MIN_SCORE + 1 {
+                let min = value + 2 * Self::MIN_SCORE - Self::MAX_SCORE - 2;
                 if alpha < min {
                     alpha = min;
                     if alpha >= beta {
@@ -165,7 +811,7 @@ impl Solver {
                 }
             // else upper bound
             } else {
-                let max = value + MIN_SCORE - 1;
+                let max = value + Self::MIN_SCORE - 1;
                 if beta > max {
                     beta = max;
                     if alpha >= beta {
@@ -174,7 +820,7 @@ impl Solver {
                     }
                 }
             }
-            max = value + MIN_SCORE - 1;
+            max = value + Self::MIN_SCORE - 1;
         }
         if beta > max {
             // clamp beta to calculated upper bound
@@ -185,32 +831,43 @@ impl Solver {
             };
         }
 
-        let mut moves = MoveSorter::new();
+        let non_losing_moves = self.moves_to_search(non_losing_moves);
+        let mut moves = MoveSorter::<W>::new();
         // reversing move order to put edges first reduces the amount of sorting
         // as these moves are worse on average
-        for i in (0..WIDTH).rev() {
-            let column = move_order()[i];
-            let candidate = non_losing_moves & BitBoard::column_mask(column);
+        for i in (0..W).rev() {
+            let column = column_order::<W>()[i];
+            let candidate = non_losing_moves & GenericBitBoard::<W, H>::column_mask(column);
             if candidate != 0 && self.board.playable(column) {
-                moves.push(candidate, column, self.board.move_score(candidate))
+                moves.push(
+                    candidate,
+                    column,
+                    self.order_score(candidate, column, killers[self.board.num_moves()]),
+                )
             }
         }
 
         // search the next level of the tree
-        for (move_bitmap, _column) in moves {
+        for (move_bitmap, column) in moves {
             let mut next = self.clone();
             next.node_count = 0;
 
             next.board.play(move_bitmap);
             // the search window is flipped for the other player
-            let score = -next.negamax(-beta, -alpha);
+            let score = -next.negamax(-beta, -alpha, hook, killers);
             self.node_count += next.node_count;
             // if a child node's score is better than beta, we can prune the tree
             // here because a perfect opponent will not pick this branch
             if score >= beta {
+                if self.uses_killer_moves() {
+                    killers[self.board.num_moves()] = column as u8 + 1;
+                }
                 // save a lower bound of the score
-                self.transposition_table
-                    .set(key, (score + MAX_SCORE - 2 * MIN_SCORE + 2) as u8);
+                self.transposition_table.set_at_depth(
+                    key,
+                    (score + Self::MAX_SCORE - 2 * Self::MIN_SCORE + 2) as u8,
+                    self.board.num_moves(),
+                );
                 return score;
             }
             if score > alpha {
@@ -219,62 +876,72 @@ impl Solver {
         }
 
         // offset of one to prevent putting a 0, which represents an empty entry
-        self.transposition_table
-            .set(self.board.key(), (alpha - MIN_SCORE + 1) as u8);
+        self.transposition_table.set_at_depth(
+            key,
+            (alpha - Self::MIN_SCORE + 1) as u8,
+            self.board.num_moves(),
+        );
         alpha
     }
 
     /// Performs a top-level search, bypassing transposition table and opening database
     ///
     /// Returns the score of the position and the calculated best move
-    fn top_level_search(&mut self, mut alpha: i32, beta: i32) -> (i32, usize) {
+    fn top_level_search<Hook: SearchHook>(
+        &mut self,
+        mut alpha: i32,
+        beta: i32,
+        hook: &mut Hook,
+        killers: &mut Killers,
+    ) -> (i32, usize) {
         self.node_count += 1;
+        hook.visit_node();
 
         // check for win for current player on this move
-        for column in 0..WIDTH {
+        for column in 0..W {
             if self.board.playable(column) && self.board.check_winning_move(column) {
-                return (
-                    ((WIDTH * HEIGHT + 1 - self.board.num_moves()) / 2) as i32,
-                    column,
-                );
+                return (((W * H + 1 - self.board.num_moves()) / 2) as i32, column);
             }
         }
 
+        // check for draw (no valid moves), before looking for non-losing moves as there are none
+        if self.board.is_full() {
+            return (0, W);
+        }
+
         // look for moves that don't give the opponent a next turn win
         let non_losing_moves = self.board.non_losing_moves();
         if non_losing_moves == 0 {
             // all moves lose, return the first legal move found
-            let first = (0..WIDTH).find(|&i| self.board.playable(i)).unwrap();
-            return (
-                -((WIDTH * HEIGHT) as i32 - self.board.num_moves() as i32) / 2,
-                first,
-            );
+            let first = (0..W).find(|&i| self.board.playable(i)).unwrap();
+            return (-((W * H) as i32 - self.board.num_moves() as i32) / 2, first);
         }
 
-        // check for draw (no valid moves)
-        if self.board.num_moves() == WIDTH * HEIGHT {
-            return (0, WIDTH);
-        }
-
-        let mut moves = MoveSorter::new();
-        for i in (0..WIDTH).rev() {
-            let column = move_order()[i];
-            let candidate = non_losing_moves & BitBoard::column_mask(column);
+        // symmetric positions like the empty board only need half of their moves searching
+        let non_losing_moves = self.root_moves_to_search(non_losing_moves);
+        let mut moves = MoveSorter::<W>::new();
+        for i in (0..W).rev() {
+            let column = column_order::<W>()[i];
+            let candidate = non_losing_moves & GenericBitBoard::<W, H>::column_mask(column);
             if candidate != 0 && self.board.playable(column) {
-                moves.push(candidate, column, self.board.move_score(candidate))
+                moves.push(
+                    candidate,
+                    column,
+                    self.order_score(candidate, column, killers[self.board.num_moves()]),
+                )
             }
         }
 
         // search the next level of the tree and keep track of the best move
-        let mut best_score = MIN_SCORE;
-        let mut best_move = WIDTH;
+        let mut best_score = Self::MIN_SCORE;
+        let mut best_move = W;
         for (move_bitmap, column) in moves {
             let mut next = self.clone();
             next.node_count = 0;
 
             next.board.play(move_bitmap);
             // the search window is flipped for the other player
-            let score = -next.negamax(-beta, -alpha);
+            let score = -next.negamax(-beta, -alpha, hook, killers);
             self.node_count += next.node_count;
             // if the actual score is better than beta, we can prune the tree
             // because the other player will not pick this branch
@@ -294,23 +961,305 @@ impl Solver {
     }
 
     /// Calculate the score and best move of the current position with iterative deepening
+    ///
+    /// # Notes
+    /// The number of threads configured with a [`SolverBuilder`] is used here, searching in
+    /// parallel as in [`solve_parallel`](Self::solve_parallel). The configured timeout isn't,
+    /// the score is always exact (see [`solve_timed`](Self::solve_timed))
+    ///
+    /// [`SolverBuilder`]: struct.GenericSolverBuilder.html
     pub fn solve(&mut self) -> (i32, usize) {
-        self._solve(true)
+        #[cfg(feature = "native")]
+        if self.threads.is_some() {
+            return self._solve_configured(None);
+        }
+        let stats = self.solve_with_stats();
+        (stats.score, stats.best_move)
+    }
+
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// stopping early once the timeout configured with a [`SolverBuilder`] has passed
+    ///
+    /// Returns the same as [`solve_with_timeout`](Self::solve_with_timeout), so the score is
+    /// only exact when the lowest and highest scores are equal. Without a timeout this searches
+    /// until the score is exact. The configured number of threads is used as in
+    /// [`solve`](Self::solve)
+    ///
+    /// [`SolverBuilder`]: struct.GenericSolverBuilder.html
+    #[cfg(feature = "native")]
+    pub fn solve_timed(&mut self) -> (i32, usize, (i32, i32)) {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let (score, next_move) = self._solve_configured(deadline);
+        (score, next_move, self.current_bounds())
+    }
+
+    /// Performs the iterative deepening search until `deadline` with the configured number of
+    /// threads, returning position score and best move
+    #[cfg(feature = "native")]
+    fn _solve_configured(&mut self, deadline: Option<Deadline>) -> (i32, usize) {
+        // the thread count can only be set with a shared table
+        match self.threads.zip(self.transposition_table.as_shared()) {
+            Some((threads, table)) => {
+                let mut solver = self.with_transposition_table(table);
+                let result = solver._solve_parallel_until(threads, deadline);
+                self.node_count = solver.node_count;
+                self.bounds = solver.bounds;
+                result
+            }
+            None => {
+                let (score, next_move, _) = self._solve_until(deadline, &mut |_| {}, &mut ());
+                (score, next_move)
+            }
+        }
+    }
+
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// measuring the nodes searched and the time taken
+    pub fn solve_with_stats(&mut self) -> SearchStats {
+        let start_nodes = self.node_count;
+        #[cfg(feature = "native")]
+        let start_time = Instant::now();
+        let (score, best_move) = self._solve(&mut ());
+        #[cfg(feature = "native")]
+        let elapsed = start_time.elapsed();
+        #[cfg(not(feature = "native"))]
+        let elapsed = Duration::default();
+
+        SearchStats {
+            score,
+            best_move,
+            nodes: self.node_count - start_nodes,
+            elapsed,
+            depth_reached: self.search_depth(score, score),
+        }
     }
     
     /// Calculate the score and best move of the current position with iterative deepening, logging progress to stdout
+    #[cfg(feature = "std")]
     pub fn solve_verbose(&mut self) -> (i32, usize) {
-        self._solve(false)
+        let empty_squares = W * H - self.board.num_moves();
+        self.solve_with_progress(|progress| {
+            println!(
+                "Search depth: {}/{}, uncertainty: {}",
+                progress.depth,
+                empty_squares,
+                progress.window_high - progress.window_low
+            )
+        })
+    }
+
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// calling `callback` with the progress of the search at the start of each iteration
+    ///
+    /// # Notes
+    /// The score window of each iteration is narrower than the last, so the callback can be used
+    /// to draw a progress bar or show the bounds on the score of the position
+    pub fn solve_with_progress<F: FnMut(SearchProgress)>(
+        &mut self,
+        mut callback: F,
+    ) -> (i32, usize) {
+        let (score, next_move, _) = self._solve_until(None, &mut callback, &mut ());
+        (score, next_move)
+    }
+
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// calling `yield_fn` after every `yield_every` nodes searched
+    ///
+    /// # Notes
+    /// This allows long searches to cooperate with a scheduler, for example by yielding to an
+    /// async executor or checking a time budget. A `yield_every` of 0 is treated as 1
+    pub fn solve_yielding(&mut self, yield_every: usize, yield_fn: impl FnMut()) -> (i32, usize) {
+        let mut yielder = Yielder {
+            yield_every: yield_every.max(1),
+            remaining: yield_every.max(1),
+            yield_fn,
+        };
+        self._solve(&mut yielder)
+    }
+
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// stopping early once `limit` has passed
+    ///
+    /// Returns the score, the best move found and the lowest and highest scores the position can
+    /// have (see [`current_bounds`](Self::current_bounds)), which are equal if the score is exact
+    ///
+    /// # Notes
+    /// The time limit is checked between the null-window searches of the iterative deepening, so
+    /// a search in progress is always finished. When the search is cut off, the returned score is
+    /// a lower bound on the position score that the returned move achieves
+    #[cfg(feature = "native")]
+    pub fn solve_with_timeout(&mut self, limit: Duration) -> (i32, usize, (i32, i32)) {
+        let (score, next_move, _) =
+            self._solve_until(Some(Instant::now() + limit), &mut |_| {}, &mut ());
+        (score, next_move, self.current_bounds())
+    }
+
+    /// Performs a single null-window search around `gamma`, returning a bound on the score of the
+    /// position and the best move found
+    ///
+    /// # Notes
+    /// The search is fail-soft: a returned score `r > gamma` means the true score is at least `r`,
+    /// otherwise the true score is at most `r`. Repeating the search with different values of
+    /// `gamma` narrows the score down, as [`solve`](Self::solve) does. The returned move is only
+    /// guaranteed to be the best move when the search fails high
+    pub fn null_window_search(&mut self, gamma: i32) -> (i32, usize) {
+        self.top_level_search(gamma, gamma + 1, &mut (), &mut [0; 64])
+    }
+
+    /// Calculate whether the current position is a win, draw or loss for the player to move,
+    /// and a move that achieves it
+    ///
+    /// Returns `Greater` for a win, `Equal` for a draw and `Less` for a loss
+    ///
+    /// # Notes
+    /// The search window is `[-1, 1]` instead of the full score range, so only the sign of the
+    /// score is calculated, which is much faster than [`solve`](Self::solve). As the distance to
+    /// the end of the game isn't known, a winning move may not be the fastest win. For a full
+    /// board, `W` is returned as the move
+    pub fn solve_weak(&mut self) -> (Ordering, usize) {
+        // a full board is a draw with no moves left, as with `solve`
+        if self.board.is_full() {
+            return (Ordering::Equal, W);
+        }
+        let (score, best_move) = self.top_level_search(-1, 1, &mut (), &mut [0; 64]);
+        (score.cmp(&0), best_move)
+    }
+
+    /// Returns whether the player to move can force a win
+    ///
+    /// This only performs a single null-window search, which is cheaper than calculating the
+    /// exact score with [`solve`](Self::solve)
+    pub fn is_winning(&mut self) -> bool {
+        self.null_window_search(0).0 > 0
+    }
+
+    /// Returns whether the opponent of the player to move can force a win
+    ///
+    /// This only performs a single null-window search, which is cheaper than calculating the
+    /// exact score with [`solve`](Self::solve)
+    pub fn is_losing(&mut self) -> bool {
+        self.null_window_search(-1).0 < 0
+    }
+
+    /// Calculate the exact score and best move of the current position, only considering the
+    /// given columns as moves for the current player
+    ///
+    /// # Notes
+    /// The score is the best score among the given columns, which may be worse than the score
+    /// of the position if the best move is not one of them. Moves that give the opponent an
+    /// immediate win are scored without searching
+    ///
+    /// # Panics
+    /// Panics if none of the given columns are legal moves
+    pub fn solve_restricted(&mut self, columns: &[usize]) -> (i32, usize) {
+        let mut best: Option<(i32, usize)> = None;
+
+        for &column in columns {
+            if !self.board.playable(column) {
+                continue;
+            }
+            let score = self.column_score(column, &mut ());
+
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, column));
+            }
+        }
+        best.expect("No legal columns to search")
+    }
+
+    /// Calculate the score and best move of the current position as if `extra_tempo_for` was
+    /// given an extra move, for handicapped play
+    ///
+    /// # Notes
+    /// Passing is not a legal move, so the extra move is made by a null move, where the other
+    /// player passes their turn. If `extra_tempo_for` is not to move, the current player passes
+    /// and the best move is for `extra_tempo_for`. Otherwise the returned move is played and then
+    /// the opponent passes, giving the current player two moves in a row.
+    ///
+    /// The score is from the perspective of `extra_tempo_for`, counted from the position where
+    /// they are to move after the pass (see [Position Scoring]). A full board is a draw with a
+    /// best move of `W`, as with [`solve`](Self::solve)
+    ///
+    /// [Position Scoring]: #position-scoring
+    pub fn solve_with_tempo(&mut self, extra_tempo_for: Player) -> (i32, usize) {
+        if self.board.is_full() {
+            return (0, W);
+        }
+        if self.board.current_player() != extra_tempo_for {
+            let mut next = self.null_move_solver();
+            let result = next.solve();
+            self.node_count += next.node_count;
+            return result;
+        }
+
+        let mut best: Option<(i32, usize)> = None;
+        for &column in column_order::<W>().iter() {
+            if !self.board.playable(column) {
+                continue;
+            }
+            let score = if self.board.check_winning_move(column) {
+                ((W * H + 1 - self.board.num_moves()) / 2) as i32
+            } else {
+                // play the move, then pass back to the current player
+                let mut next = self.clone();
+                next.board.play(
+                    self.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column),
+                );
+                let mut next = next.null_move_solver();
+                let (score, _) = next.solve();
+                self.node_count += next.node_count;
+                score
+            };
+
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, column));
+            }
+        }
+        best.expect("No legal moves to play")
+    }
+
+    /// Returns a copy of this `Solver` where the current player has passed their turn
+    fn null_move_solver(&self) -> Self {
+        let mut next = self.clone();
+        next.node_count = 0;
+        next.prefer_slow_wins = false;
+        // keep the move counter, as the search only uses it to count the empty squares
+        next.board = GenericBitBoard::<W, H>::from_parts(
+            self.board.player_mask() ^ self.board.board_mask(),
+            self.board.board_mask(),
+            self.board.num_moves(),
+        );
+        next
     }
 
     /// Performs the iterative deepening search, returning position score and best move
-    fn _solve(&mut self, silent: bool) -> (i32, usize) {
-        let mut min = -(((WIDTH * HEIGHT) as i32) - self.board.num_moves() as i32) / 2;
-        let mut max = (WIDTH * HEIGHT + 1 - self.board.num_moves()) as i32 / 2;
+    fn _solve<Hook: SearchHook>(&mut self, hook: &mut Hook) -> (i32, usize) {
+        let (score, next_move, _) = self._solve_until(None, &mut |_| {}, hook);
+        (score, next_move)
+    }
+
+    /// Performs the iterative deepening search until `deadline`, calling `progress` at the start
+    /// of each iteration, returning position score, best move and whether the score is exact
+    fn _solve_until<P: FnMut(SearchProgress), Hook: SearchHook>(
+        &mut self,
+        deadline: Option<Deadline>,
+        progress: &mut P,
+        hook: &mut Hook,
+    ) -> (i32, usize, bool) {
+        let start_nodes = self.node_count;
+        let (mut min, mut max) = self.score_window();
 
-        let mut next_move = WIDTH;
+        let mut next_move = W;
+        // killer moves are kept between iterations, as the same moves often cause cutoffs
+        let mut killers = [0; 64];
+        // the move proven to score at least `min`, which any legal move does at first
+        let mut lower_bound_move = self.fallback_move();
         // iteratively narrow the search window for iterative deepening
         while min < max {
+            self.bounds = Some((min, max));
+            if deadline.is_some_and(deadline_passed) {
+                return (min, lower_bound_move, false);
+            }
             let mut mid = min + (max - min) / 2;
             // tweak the search value for both negative and positive searches
             if mid <= 0 && min / 2 < mid {
@@ -319,18 +1268,15 @@ impl Solver {
                 mid = max / 2
             }
 
-            // log progress to stdout
-            if !silent {
-                println!(
-                    "Search depth: {}/{}, uncertainty: {}",
-                    (WIDTH * HEIGHT - self.board.num_moves()) as i32 - min.abs().min(max.abs()),
-                    WIDTH * HEIGHT - self.board.num_moves(),
-                    max - min
-                );
-            }
+            progress(SearchProgress {
+                depth: self.search_depth(min, max),
+                window_low: min,
+                window_high: max,
+                nodes: self.node_count - start_nodes,
+            });
 
             // use a null-window to determine if the actual score is greater or less that mid
-            let (r, best_move) = self.top_level_search(mid, mid + 1);
+            let (r, best_move) = self.top_level_search(mid, mid + 1, hook, &mut killers);
             next_move = best_move;
 
             // r is not necessarily the exact true score, but its value indicates
@@ -341,28 +1287,808 @@ impl Solver {
             } else {
                 // actual score > mid
                 min = r;
+                lower_bound_move = best_move;
             }
         }
         // min and max should be equal here
-        (min, next_move)
+        self.bounds = Some((min, max));
+        if self.prefer_slow_wins && min > 0 {
+            if let Some(column) = self.slowest_winning_move(hook) {
+                next_move = column;
+            }
+        }
+        (min, next_move, true)
+    }
+
+    /// Returns a legal move without searching, preferring immediate wins, then moves that don't
+    /// lose immediately, or `W` if the board is full
+    fn fallback_move(&self) -> usize {
+        let playable = |column: &usize| self.board.playable(*column);
+        let non_losing_moves = self.board.non_losing_moves();
+        let order = column_order::<W>();
+
+        order
+            .iter()
+            .copied()
+            .filter(playable)
+            .find(|&column| self.board.check_winning_move(column))
+            .or_else(|| {
+                order.iter().copied().filter(playable).find(|&column| {
+                    non_losing_moves & GenericBitBoard::<W, H>::column_mask(column) != 0
+                })
+            })
+            .or_else(|| order.iter().copied().find(playable))
+            .unwrap_or(W)
+    }
+
+    /// Returns the search depth reached once the score is known to be within `[min, max]`
+    fn search_depth(&self, min: i32, max: i32) -> usize {
+        (W * H - self.board.num_moves()).saturating_sub(min.abs().min(max.abs()) as usize)
+    }
+
+    /// Returns the winning move with the furthest forced win, or `None` if no move wins
+    fn slowest_winning_move<Hook: SearchHook>(&mut self, hook: &mut Hook) -> Option<usize> {
+        let non_losing_moves = self.board.non_losing_moves();
+        // score and column of the slowest win found so far
+        let mut slowest: Option<(i32, usize)> = None;
+
+        for &column in column_order::<W>().iter() {
+            // moves that lose immediately can't win
+            if !self.board.playable(column)
+                || !self.board.check_winning_move(column)
+                    && non_losing_moves & GenericBitBoard::<W, H>::column_mask(column) == 0
+            {
+                continue;
+            }
+            let score = self.column_score(column, hook);
+
+            if score > 0 && slowest.is_none_or(|(slowest_score, _)| score < slowest_score) {
+                slowest = Some((score, column));
+            }
+        }
+        slowest.map(|(_, column)| column)
+    }
+
+    /// Calculates the exact score of playing a legal column, from the perspective of the current player
+    fn column_score<Hook: SearchHook>(&mut self, column: usize, hook: &mut Hook) -> i32 {
+        if self.board.check_winning_move(column) {
+            return ((W * H + 1 - self.board.num_moves()) / 2) as i32;
+        }
+        if self.board.non_losing_moves() & GenericBitBoard::<W, H>::column_mask(column) == 0 {
+            // the opponent wins on their next move
+            return -((W * H) as i32 - self.board.num_moves() as i32) / 2;
+        }
+        // solve the child position exactly, sharing the transposition table
+        let mut next = self.clone();
+        next.node_count = 0;
+        next.prefer_slow_wins = false;
+
+        next.board
+            .play(self.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column));
+        let (score, _) = next._solve(hook);
+        self.node_count += next.node_count;
+        -score
+    }
+
+    /// Plays a full game from `board` with the engine choosing every move, sharing `table`
+    /// between the searches
+    ///
+    /// Returns the moves played (0-indexed columns) and the result of the game, scored from
+    /// the perspective of the player to move in `board` (see [Position Scoring])
+    ///
+    /// [Position Scoring]: #position-scoring
+    pub fn self_play(mut board: GenericBitBoard<W, H>, table: &T) -> (Vec<usize>, i32) {
+        let start_moves = board.num_moves();
+        let mut moves = Vec::new();
+
+        while !board.is_full() {
+            let mut solver = Self::new_with_transposition_table(board, table.clone());
+            let (_, column) = solver.solve();
+            let win = board.check_winning_move(column);
+
+            board.play(board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column));
+            moves.push(column);
+
+            if win {
+                // the winner is whoever moved last
+                let score = ((W * H + 2 - board.num_moves()) / 2) as i32;
+                return match moves.len() % 2 {
+                    1 => (moves, score),
+                    _ => (moves, -score),
+                };
+            }
+        }
+        debug_assert_eq!(moves.len(), W * H - start_moves);
+        (moves, 0)
+    }
+
+    /// Returns the principal variation, the sequence of moves (0-indexed columns) played when
+    /// both players choose the best move from the current position until the game ends
+    ///
+    /// # Notes
+    /// Each move is found with a full solve sharing the transposition table, so this is slow in
+    /// early positions. The sequence stops early if a search ever returns a move that can't be
+    /// played, so it is always a legal line of play
+    ///
+    /// # Example
+    /// ```
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use connect4_ai::solver::Solver;
+    ///
+    /// // the first player completes the bottom row
+    /// let mut solver = Solver::from_moves("112233")?;
+    /// assert_eq!(solver.principal_variation(), vec![3]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn principal_variation(&mut self) -> Vec<usize> {
+        let mut next = self.clone();
+        next.node_count = 0;
+
+        let mut moves = Vec::new();
+        while !next.board.is_full() {
+            let (_, column) = next.solve();
+            if column >= W || !next.board.playable(column) {
+                break;
+            }
+            let win = next.board.check_winning_move(column);
+
+            next.board
+                .play(next.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column));
+            moves.push(column);
+            if win {
+                break;
+            }
+        }
+        self.node_count += next.node_count;
+        moves
+    }
+
+    /// Calculates the exact score of every legal move, sorted from best to worst
+    ///
+    /// # Notes
+    /// Moves with equal scores are ordered from the centre column outwards. If
+    /// `include_losing_moves` is `false`, moves that give the opponent an immediate win are
+    /// omitted, unless every move does
+    pub fn ranked_moves(&mut self, include_losing_moves: bool) -> Vec<(usize, i32)> {
+        let non_losing_moves = self.board.non_losing_moves();
+        let mut moves = Vec::with_capacity(W);
+
+        for &column in column_order::<W>().iter() {
+            if !self.board.playable(column) {
+                continue;
+            }
+            let loses = !self.board.check_winning_move(column)
+                && non_losing_moves & GenericBitBoard::<W, H>::column_mask(column) == 0;
+            if include_losing_moves || !loses || non_losing_moves == 0 {
+                moves.push((column, self.column_score(column, &mut ())));
+            }
+        }
+        // the sort is stable, so ties keep the centre-first order
+        moves.sort_by_key(|&(_, score)| core::cmp::Reverse(score));
+        moves
+    }
+
+    /// Calculates the exact score of every column, or `None` for columns that can't be played
+    ///
+    /// # Notes
+    /// Scores are from the perspective of the player to move (see [Position Scoring]), and include
+    /// moves that give the opponent an immediate win. Every column is searched with the shared
+    /// transposition table, so later columns reuse the positions found by earlier ones
+    ///
+    /// [Position Scoring]: #position-scoring
+    pub fn analyze(&mut self) -> [Option<i32>; W] {
+        let mut scores = [None; W];
+        for &column in column_order::<W>().iter() {
+            if self.board.playable(column) {
+                scores[column] = Some(self.column_score(column, &mut ()));
+            }
+        }
+        scores
+    }
+
+    /// Calculates the exact score of the position and every column that achieves it
+    ///
+    /// # Notes
+    /// The columns are ordered from the centre outwards (see [`move_order`]), and every column
+    /// with an immediate win is included when there are several. Each column is scored with
+    /// [`analyze`](Self::analyze), so this is slower than [`solve`](Self::solve). A full board
+    /// returns a score of 0 and no moves
+    ///
+    /// [`move_order`]: fn.move_order.html
+    pub fn best_moves(&mut self) -> (i32, Vec<usize>) {
+        let scores = self.analyze();
+        let best_score = match scores.iter().flatten().max() {
+            Some(&score) => score,
+            None => return (0, Vec::new()),
+        };
+        let columns = column_order::<W>()
+            .iter()
+            .copied()
+            .filter(|&column| scores[column] == Some(best_score))
+            .collect();
+        (best_score, columns)
+    }
+
+    /// Returns how many plies of optimal play remain until the outcome of the game is locked in
+    ///
+    /// # Notes
+    /// The outcome is locked in once neither player can change whether the game is won, drawn
+    /// or lost with any of their moves, which can be long before the game ends or a player could
+    /// otherwise be forced to lose. This follows the best move at each ply and scores every legal
+    /// move along the way, so it is slow in early positions. A finished game returns 0
+    pub fn plies_until_decided(&mut self) -> usize {
+        let mut next = self.clone();
+        next.node_count = 0;
+        next.prefer_slow_wins = false;
+
+        let mut decided_at = 0;
+        let mut ply = 0;
+        while next.board.winner().is_none() && !next.board.is_full() {
+            let moves = next.ranked_moves(true);
+            let (best_move, best_score) = moves[0];
+            // some move still changes the outcome, so it isn't locked in yet
+            if moves
+                .iter()
+                .any(|&(_, score)| score.signum() != best_score.signum())
+            {
+                decided_at = ply + 1;
+            }
+            next.board.play(
+                next.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(best_move),
+            );
+            ply += 1;
+        }
+        self.node_count += next.node_count;
+        decided_at
+    }
+
+    /// Calculate the score and best move of the current position, choosing the move that delays
+    /// a forced loss the longest
+    ///
+    /// # Notes
+    /// In a lost position [`solve`](Self::solve) may return any losing move, which makes for
+    /// unsatisfying play against humans. This instead scores every move and returns the one that
+    /// loses slowest, which takes longer in lost positions. Other positions are solved as usual
+    pub fn best_defense(&mut self) -> (i32, usize) {
+        let (score, column) = self._solve(&mut ());
+        if score >= 0 {
+            return (score, column);
+        }
+        let (column, score) = self.ranked_moves(true)[0];
+        (score, column)
+    }
+
+    /// Calculate the best move of the current position and classify why it was chosen
+    ///
+    /// # Notes
+    /// The first matching [`MoveReason`] is used, so a forced block that also creates a double
+    /// threat is reported as a forced block. The game must not be over
+    ///
+    /// [`MoveReason`]: enum.MoveReason.html
+    pub fn explain(&mut self) -> MoveExplanation {
+        let (score, column) = self._solve(&mut ());
+        let move_bitmap =
+            self.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column);
+        let opponent_winning_positions = self
+            .board
+            .winning_positions(self.board.player_mask() ^ self.board.board_mask());
+        let non_losing_moves = self.board.non_losing_moves();
+
+        let mut next = self.board;
+        next.play(move_bitmap);
+
+        let reason = if self.board.check_winning_move(column) {
+            MoveReason::ImmediateWin
+        } else if move_bitmap & opponent_winning_positions != 0 {
+            MoveReason::ForcedBlock
+        } else if next.is_lost() {
+            MoveReason::DoubleThreat
+        } else if non_losing_moves == move_bitmap {
+            MoveReason::OnlyNonLosingMove
+        } else {
+            MoveReason::Positional
+        };
+
+        MoveExplanation {
+            column,
+            score,
+            reason,
+        }
+    }
+
+    /// Calculate a score and move of the current position that is only sometimes the best move,
+    /// for a configurable AI difficulty
+    ///
+    /// Returns the score of the chosen move and the chosen move
+    ///
+    /// # Notes
+    /// Levels at or above [`MAX_DIFFICULTY`] always play the best move, as in
+    /// [`solve`](Self::solve). Below that, each level down raises the chance of choosing a worse
+    /// move by 5%, up to 50%, and lets the worse move score up to 2 more below the best move.
+    /// Moves are chosen with a random number generator seeded by
+    /// [`random_seed`](Self::random_seed), so the same seed and positions always choose the
+    /// same moves. Every column is scored with [`analyze`](Self::analyze), so this is slower than
+    /// [`solve`](Self::solve) below the maximum level. A full board returns a score of 0 and `W`
+    /// as the move
+    ///
+    /// [`MAX_DIFFICULTY`]: constant.MAX_DIFFICULTY.html
+    pub fn solve_with_difficulty(&mut self, level: u8) -> (i32, usize) {
+        if level >= MAX_DIFFICULTY {
+            return self.solve();
+        }
+        let handicap = (MAX_DIFFICULTY - level) as i32;
+
+        // the scored columns from the centre outwards
+        let scores = self.analyze();
+        let moves = IntoIterator::into_iter(column_order::<W>())
+            .filter_map(|column| scores[column].map(|score| (score, column)));
+
+        let mut best: Option<(i32, usize)> = None;
+        for (score, column) in moves.clone() {
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, column));
+            }
+        }
+        let (best_score, best_move) = match best {
+            Some(best) => best,
+            None => return (0, W),
+        };
+
+        if (self.next_random() % 100) as i32 >= 5 * handicap {
+            return (best_score, best_move);
+        }
+        let worse_moves: Vec<(i32, usize)> = moves
+            .filter(|&(score, _)| score < best_score && score >= best_score - 2 * handicap)
+            .collect();
+        if worse_moves.is_empty() {
+            return (best_score, best_move);
+        }
+        worse_moves[self.next_random() as usize % worse_moves.len()]
+    }
+
+    /// Advances the linear congruential generator used for suboptimal play
+    fn next_random(&mut self) -> u32 {
+        self.random_state = self
+            .random_state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.random_state >> 33) as u32
+    }
+
+    /// Returns a reasonable but imperfect move found by searching only `lookahead` plies ahead,
+    /// for playing against beginners
+    ///
+    /// # Notes
+    /// Positions at the end of the lookahead are scored by a heuristic that favours open lines
+    /// and threats, so larger lookaheads play stronger. Immediate wins are always taken, and
+    /// moves that give the opponent an immediate win are only played when every move does. A
+    /// `lookahead` of 0 is treated as 1. The game must not be over
+    pub fn beginner_move(&mut self, lookahead: usize) -> usize {
+        let non_losing_moves = self.board.non_losing_moves();
+        let mut best: Option<(i32, usize)> = None;
+
+        for &column in column_order::<W>().iter() {
+            if !self.board.playable(column) {
+                continue;
+            }
+            if self.board.check_winning_move(column) {
+                return column;
+            }
+            let move_bitmap =
+                self.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column);
+            if non_losing_moves != 0 && non_losing_moves & move_bitmap == 0 {
+                continue;
+            }
+
+            let mut next = self.clone();
+            next.node_count = 0;
+            next.board.play(move_bitmap);
+            let score = -next.heuristic_negamax(
+                lookahead.max(1) - 1,
+                -HEURISTIC_WIN_SCORE,
+                HEURISTIC_WIN_SCORE,
+                Self::heuristic_score,
+            );
+            self.node_count += next.node_count;
+
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, column));
+            }
+        }
+        best.expect("No legal moves to play").1
+    }
+
+    /// Performs a game tree search to a fixed depth, scoring the positions at that depth with
+    /// `estimate`
+    ///
+    /// Returns the heuristic score of the position. A win decided within `depth` plies scores
+    /// `HEURISTIC_WIN_SCORE` less the number of moves played before the winning move, so faster
+    /// wins score higher and every decided score is further from 0 than any estimate
+    fn heuristic_negamax(
+        &mut self,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        estimate: fn(&Self) -> i32,
+    ) -> i32 {
+        self.node_count += 1;
+
+        let win_score = HEURISTIC_WIN_SCORE - self.board.num_moves() as i32;
+        for column in 0..W {
+            if self.board.playable(column) && self.board.check_winning_move(column) {
+                return win_score;
+            }
+        }
+        let non_losing_moves = self.board.non_losing_moves();
+        if non_losing_moves == 0 {
+            // the opponent wins with the next move
+            return -(win_score - 1);
+        }
+        if self.board.is_full() {
+            return 0;
+        }
+        if depth == 0 {
+            return estimate(self).clamp(-HEURISTIC_DECIDED_SCORE + 1, HEURISTIC_DECIDED_SCORE - 1);
+        }
+
+        let mut moves = MoveSorter::<W>::new();
+        for i in (0..W).rev() {
+            let column = column_order::<W>()[i];
+            let candidate = non_losing_moves & GenericBitBoard::<W, H>::column_mask(column);
+            if candidate != 0 {
+                moves.push(candidate, column, self.board.move_score(candidate))
+            }
+        }
+
+        for (move_bitmap, _column) in moves {
+            let mut next = self.clone();
+            next.node_count = 0;
+
+            next.board.play(move_bitmap);
+            let score = -next.heuristic_negamax(depth - 1, -beta, -alpha, estimate);
+            self.node_count += next.node_count;
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+
+    /// Estimates how good the current position is for the player to move, from the difference
+    /// in open lines (see [`BitBoard::open_lines`]) and threatened squares of each player
+    fn heuristic_score(&self) -> i32 {
+        let (player, opponent) = match self.board.current_player() {
+            Player::One => (Player::One, Player::Two),
+            Player::Two => (Player::Two, Player::One),
+        };
+        let threats = |player| {
+            self.board
+                .winning_positions(self.board.tiles(player))
+                .count_ones() as i32
+        };
+
+        self.board.open_lines(player) as i32 - self.board.open_lines(opponent) as i32
+            + 4 * (threats(player) - threats(opponent))
+    }
+
+    /// Calculate the score and best move of the current position by searching at most
+    /// `max_depth` plies ahead, for fast but imperfect play
+    ///
+    /// # Notes
+    /// Positions decided within `max_depth` plies are scored exactly (see [Position Scoring]).
+    /// Positions at the depth limit are scored by the threats the player to move can create
+    /// with their best next move (see [`BitBoard::move_score`]) less the opponent's threats, so
+    /// the returned score is only a heuristic estimate and not the game-theoretic value of the
+    /// position unless the outcome is decided within `max_depth` plies. Decided outcomes are
+    /// always preferred to estimates, so a forced win is never passed up for a position that
+    /// only looks good. This is the search of [`beginner_move`](Self::beginner_move) with a
+    /// different estimate. A `max_depth` of 0 is treated as 1. A full board returns `(0, W)`
+    ///
+    /// [Position Scoring]: #position-scoring
+    /// [`BitBoard::move_score`]: ../bitboard/struct.GenericBitBoard.html#method.move_score
+    pub fn solve_depth_limited(&mut self, max_depth: usize) -> (i32, usize) {
+        if self.board.is_full() {
+            return (0, W);
+        }
+
+        let mut alpha = -HEURISTIC_WIN_SCORE;
+        let mut best_move = self.fallback_move();
+        for &column in column_order::<W>().iter() {
+            if !self.board.playable(column) {
+                continue;
+            }
+            let score = if self.board.check_winning_move(column) {
+                HEURISTIC_WIN_SCORE - self.board.num_moves() as i32
+            } else {
+                let mut next = self.clone();
+                next.node_count = 0;
+                next.board.play(
+                    self.board.possible_moves() & GenericBitBoard::<W, H>::column_mask(column),
+                );
+                let score = -next.heuristic_negamax(
+                    max_depth.max(1) - 1,
+                    -HEURISTIC_WIN_SCORE,
+                    -alpha,
+                    Self::threat_score,
+                );
+                self.node_count += next.node_count;
+                score
+            };
+
+            if score > alpha {
+                alpha = score;
+                best_move = column;
+            }
+        }
+
+        // convert decided outcomes back to position scores
+        if alpha.abs() >= HEURISTIC_DECIDED_SCORE {
+            let moves_before_win = (HEURISTIC_WIN_SCORE - alpha.abs()) as usize;
+            alpha = alpha.signum() * ((W * H + 1 - moves_before_win) / 2) as i32;
+        }
+        (alpha, best_move)
+    }
+
+    /// Estimates how good the current position is for the player to move, from the most threats
+    /// they can create with one move less the opponent's threats
+    fn threat_score(&self) -> i32 {
+        let non_losing_moves = self.board.non_losing_moves();
+        let best_move_score = (0..W)
+            .map(|column| non_losing_moves & GenericBitBoard::<W, H>::column_mask(column))
+            .filter(|&candidate| candidate != 0)
+            .map(|candidate| self.board.move_score(candidate))
+            .max()
+            .unwrap_or(0);
+        best_move_score - self.board.opponent_threats().count_ones() as i32
+    }
+
+    /// Estimates how difficult the current position is to play correctly
+    ///
+    /// # Notes
+    /// This scores every legal move individually with [`analyze`](Self::analyze). A large
+    /// `best_move_margin` with few non-losing moves indicates an 'obvious' position, while
+    /// a small margin indicates a 'tricky' one
+    pub fn complexity(&mut self) -> Complexity {
+        let start_node_count = self.node_count;
+        let mut scores: Vec<i32> = self.analyze().iter().flatten().copied().collect();
+        // sort the scores from best to worst
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+
+        Complexity {
+            node_count: self.node_count - start_node_count,
+            best_move_margin: scores.get(1).map(|second| scores[0] - second),
+            num_non_losing_moves: self.board.non_losing_moves().count_ones() as usize,
+        }
     }
 
     /// Converts a position score to a win distance in a single player's moves
+    ///
+    /// For a positive score this is the number of moves the player to move needs to win,
+    /// counting the winning move, and for a negative score the number of moves their opponent
+    /// needs. A score of 0 returns the number of empty squares
     pub fn score_to_win_distance(&self, score: i32) -> usize {
+        let num_moves = self.board.num_moves();
+        // a win on the next move scores (W * H + 1 - moves played) / 2 for the winner, and each
+        // extra move the winner needs costs 1 point
         match score.cmp(&0) {
-            Ordering::Equal => WIDTH * HEIGHT - self.board.num_moves(),
-            Ordering::Greater => {
-                (WIDTH * HEIGHT / 2 + 1 - score as usize) - self.board.num_moves() / 2
+            Ordering::Equal => W * H - num_moves,
+            Ordering::Greater => (W * H + 1 - num_moves) / 2 + 1 - score as usize,
+            Ordering::Less => (W * H - num_moves) / 2 + 1 - (-score) as usize,
+        }
+    }
+
+    /// Solves the current position, returning whether the player to move can force a win and
+    /// how soon
+    pub fn outcome(&mut self) -> Outcome {
+        let (score, best_move) = self.solve();
+        Outcome {
+            result: score.cmp(&0),
+            in_moves: self.score_to_win_distance(score),
+            best_move,
+        }
+    }
+
+    /// Converts a position score to a 0-100 'win chance' for display in casual interfaces
+    ///
+    /// # Notes
+    /// This is not a real probability, as every position is a forced win, loss or draw. A draw
+    /// maps to 50, wins map to 75-100 and losses to 0-25, with faster results further from 50
+    pub fn score_to_percentage(&self, score: i32) -> u8 {
+        // the score of winning with the next move
+        let max = ((W * H + 1 - self.board.num_moves()) / 2) as i32;
+        let score = score.clamp(-max, max);
+
+        match score.cmp(&0) {
+            Ordering::Equal => 50,
+            Ordering::Greater => (75 + 25 * score / max) as u8,
+            Ordering::Less => (25 + 25 * score / max) as u8,
+        }
+    }
+}
+
+/// Methods that search on several threads, which share the transposition table
+#[cfg(feature = "native")]
+impl<const W: usize, const H: usize> GenericSolver<W, H, SharedTranspositionTable> {
+    /// Calculate the score and best move of the current position with iterative deepening,
+    /// searching the moves of the current position on `threads` threads
+    ///
+    /// # Notes
+    /// The search windows are chosen on the calling thread as in [`solve`](Self::solve), and
+    /// each window searches the children of the current position in parallel, sharing this
+    /// `Solver`'s table. Of the moves with the best score, the first in [`move_order`] is
+    /// returned, so the result doesn't depend on how the threads are scheduled. A `threads` of 0
+    /// uses rayon's global thread pool, with one thread per CPU, otherwise a thread pool is
+    /// created for each call
+    ///
+    /// # Panics
+    /// Panics if the thread pool can't be created
+    ///
+    /// [`move_order`]: fn.move_order.html
+    pub fn solve_parallel(&mut self, threads: usize) -> (i32, usize) {
+        self._solve_parallel_until(threads, None)
+    }
+
+    /// Performs the parallel iterative deepening search until `deadline`, returning position
+    /// score and best move
+    fn _solve_parallel_until(&mut self, threads: usize, deadline: Option<Deadline>) -> (i32, usize) {
+        let pool = (threads != 0).then(|| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Failed to create the thread pool")
+        });
+
+        // a full board is a draw with no moves left, as with `solve`
+        if self.board.is_full() {
+            return (0, W);
+        }
+        // check for win for current player on this move
+        for &column in column_order::<W>().iter() {
+            if self.board.playable(column) && self.board.check_winning_move(column) {
+                return (((W * H + 1 - self.board.num_moves()) / 2) as i32, column);
+            }
+        }
+        let non_losing_moves = self.board.non_losing_moves();
+        if non_losing_moves == 0 {
+            // all moves lose, return the first legal move found
+            let first = (0..W).find(|&i| self.board.playable(i)).unwrap();
+            return (-((W * H) as i32 - self.board.num_moves() as i32) / 2, first);
+        }
+
+        let worker = GenericSolver {
+            board: self.board,
+            node_count: 0,
+            transposition_table: self.transposition_table.clone(),
+            opening_database: self.opening_database.clone(),
+            prefer_slow_wins: self.prefer_slow_wins,
+            fast_move_ordering: self.fast_move_ordering,
+            prune_symmetric_moves: self.prune_symmetric_moves,
+            canonical_table_keys: self.canonical_table_keys,
+            killer_moves: self.killer_moves,
+            bounds: None,
+            random_state: self.random_state,
+            timeout: None,
+            threads: None,
+        };
+        let candidates = worker.root_moves_to_search(non_losing_moves);
+        let moves: Vec<(u64, usize)> = column_order::<W>()
+            .iter()
+            .map(|&column| {
+                let candidate = candidates & GenericBitBoard::<W, H>::column_mask(column);
+                (candidate, column)
+            })
+            .filter(|&(candidate, column)| candidate != 0 && self.board.playable(column))
+            .collect();
+
+        // searches every move with a null window around `gamma` in parallel, returning the
+        // score of each move searched, stopping early once one scores above `gamma`
+        let search = |gamma: i32| -> Vec<Option<(i32, usize)>> {
+            let failed_high = AtomicBool::new(false);
+            install(pool.as_ref(), || {
+                moves
+                    .par_iter()
+                    .map(|&(move_bitmap, _)| {
+                        if failed_high.load(atomic::Ordering::Relaxed) {
+                            return None;
+                        }
+                        let mut next = worker.clone();
+                        next.board.play(move_bitmap);
+                        let score = -next.negamax(-(gamma + 1), -gamma, &mut (), &mut [0; 64]);
+                        if score > gamma {
+                            failed_high.store(true, atomic::Ordering::Relaxed);
+                        }
+                        Some((score, next.node_count))
+                    })
+                    .collect()
+            })
+        };
+
+        let (mut min, mut max) = self.score_window();
+        // iteratively narrow the search window for iterative deepening
+        while min < max {
+            self.bounds = Some((min, max));
+            if deadline.is_some_and(deadline_passed) {
+                break;
+            }
+            let mut mid = min + (max - min) / 2;
+            // tweak the search value for both negative and positive searches
+            if mid <= 0 && min / 2 < mid {
+                mid = min / 2
+            } else if mid >= 0 && max / 2 > mid {
+                mid = max / 2
             }
-            Ordering::Less => {
-                (WIDTH * HEIGHT / 2 + 1) - (-score as usize) - self.board.num_moves() / 2
+
+            let results: Vec<(i32, usize)> = search(mid).into_iter().flatten().collect();
+            self.node_count += results.iter().map(|&(_, nodes)| nodes).sum::<usize>();
+            // moves are only skipped once another move fails high, so the best score is a
+            // bound on the position score on the same side of `mid`
+            let r = results.iter().map(|&(score, _)| score).max().unwrap_or(min);
+            if r <= mid {
+                max = r
+            } else {
+                min = r;
             }
         }
+
+        self.bounds = Some((min, max));
+
+        // the best moves are the ones scoring at least `min`, which some move does even when the
+        // search was cut off
+        let node_count = AtomicUsize::new(0);
+        let best_move = install(pool.as_ref(), || {
+            moves.par_iter().find_first(|&&(move_bitmap, _)| {
+                let mut next = worker.clone();
+                next.board.play(move_bitmap);
+                let score = -next.negamax(-min, -(min - 1), &mut (), &mut [0; 64]);
+                node_count.fetch_add(next.node_count, atomic::Ordering::Relaxed);
+                score >= min
+            })
+        });
+        self.node_count += node_count.into_inner();
+        let mut next_move = best_move.map_or_else(|| self.fallback_move(), |&(_, column)| column);
+        if self.prefer_slow_wins && min > 0 && min == max {
+            if let Some(column) = self.slowest_winning_move(&mut ()) {
+                next_move = column;
+            }
+        }
+        (min, next_move)
+    }
+}
+
+/// Runs `op` in `pool`, or in rayon's global thread pool if `pool` is `None`
+#[cfg(feature = "native")]
+fn install<R: Send>(pool: Option<&rayon::ThreadPool>, op: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Methods that rely on the opening database and table entries of the standard board size
+impl<T: TranspositionStore> GenericSolver<WIDTH, HEIGHT, T> {
+    /// Adds an opening database to an existing `Solver`
+    ///
+    /// Positions with fewer tiles than the database depth are searched down to that depth,
+    /// where the stored scores are used as exact leaf values instead of searching further
+    #[cfg(feature = "native")]
+    pub fn with_opening_database(mut self, opening_database: OpeningDatabase) -> Self {
+        self.opening_database = Some(opening_database);
+        self
+    }
+
+    /// Looks up the bound the transposition table stores for a position, if any
+    pub fn probe_table(&self, board: &BitBoard) -> Option<TableEntry> {
+        TableEntry::from_value(self.transposition_table.get(self.table_key(board)))
     }
 }
 
-impl std::ops::Deref for Solver {
-    type Target = BitBoard;
+impl<const W: usize, const H: usize, T> core::ops::Deref for GenericSolver<W, H, T> {
+    type Target = GenericBitBoard<W, H>;
 
     fn deref(&self) -> &Self::Target {
         &self.board
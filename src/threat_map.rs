@@ -0,0 +1,222 @@
+//! Incrementally updated maps of the squares that would complete an alignment for each player
+
+use alloc::vec::Vec;
+
+use crate::{bitboard::*, HEIGHT, WIDTH};
+
+/// The most four-in-a-row windows that can pass through a single square
+const MAX_SQUARE_WINDOWS: usize = 16;
+
+/// Every four-in-a-row window through each square, indexed by bit index and padded with 0s
+const SQUARE_WINDOWS: [[u64; MAX_SQUARE_WINDOWS]; WIDTH * (HEIGHT + 1)] = square_windows();
+
+/// Returns the bit index of a square
+const fn square_index(column: i32, row: i32) -> usize {
+    (column * (HEIGHT as i32 + 1) + row) as usize
+}
+
+const fn square_windows() -> [[u64; MAX_SQUARE_WINDOWS]; WIDTH * (HEIGHT + 1)] {
+    let mut windows = [[0; MAX_SQUARE_WINDOWS]; WIDTH * (HEIGHT + 1)];
+    let mut counts = [0; WIDTH * (HEIGHT + 1)];
+
+    // horizontal, vertical and both diagonal directions as (column, row) steps
+    let directions = [(1, 0), (0, 1), (1, 1), (1, -1)];
+    let mut d = 0;
+    while d < directions.len() {
+        let (dc, dr) = directions[d];
+        let mut column = 0;
+        while column < WIDTH as i32 {
+            let mut row = 0;
+            while row < HEIGHT as i32 {
+                let (end_column, end_row) = (column + 3 * dc, row + 3 * dr);
+                if end_column < WIDTH as i32 && end_row >= 0 && end_row < HEIGHT as i32 {
+                    let mut window = 0;
+                    let mut i = 0;
+                    while i < 4 {
+                        window |= 1 << square_index(column + i * dc, row + i * dr);
+                        i += 1;
+                    }
+                    // add the window to each of its squares
+                    let mut i = 0;
+                    while i < 4 {
+                        let square = square_index(column + i * dc, row + i * dr);
+                        windows[square][counts[square]] = window;
+                        counts[square] += 1;
+                        i += 1;
+                    }
+                }
+                row += 1;
+            }
+            column += 1;
+        }
+        d += 1;
+    }
+    windows
+}
+
+/// Returns an iterator over the four-in-a-row windows through a square, given as a bitmap
+pub(crate) fn square_windows_of(square: u64) -> impl Iterator<Item = u64> {
+    SQUARE_WINDOWS[square.trailing_zeros() as usize]
+        .iter()
+        .copied()
+        .take_while(|&window| window != 0)
+}
+
+/// Returns whether `square` would complete an alignment of `player_tiles`
+fn is_threat(square: u64, player_tiles: u64) -> bool {
+    square_windows_of(square).any(|window| window & !square & !player_tiles == 0)
+}
+
+/// The empty squares that would complete an alignment for each player
+///
+/// # Notes
+/// Playing a tile can only create threats in the windows through that tile, so a `ThreatMap`
+/// can be updated by checking at most 16 windows instead of recalculating the whole board. See
+/// [`ThreatBoard`] for a board that keeps its `ThreatMap` up to date
+///
+/// [`ThreatBoard`]: struct.ThreatBoard.html
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ThreatMap {
+    player_one: u64,
+    player_two: u64,
+}
+
+impl ThreatMap {
+    /// Calculates the threat map of a board from scratch
+    pub fn new(board: &BitBoard) -> Self {
+        Self {
+            player_one: board.winning_positions(board.tiles(Player::One)),
+            player_two: board.winning_positions(board.tiles(Player::Two)),
+        }
+    }
+
+    /// Returns a mask of the empty squares that would complete an alignment for a player
+    pub fn threats(&self, player: Player) -> u64 {
+        match player {
+            Player::One => self.player_one,
+            Player::Two => self.player_two,
+        }
+    }
+
+    fn threats_mut(&mut self, player: Player) -> &mut u64 {
+        match player {
+            Player::One => &mut self.player_one,
+            Player::Two => &mut self.player_two,
+        }
+    }
+
+    /// Updates the map after `player` places a tile on `square`, given the board afterwards
+    fn add_tile(&mut self, square: u64, player: Player, board: &BitBoard) {
+        // a filled square can't be a threat for either player
+        self.player_one &= !square;
+        self.player_two &= !square;
+
+        let player_tiles = board.tiles(player);
+        let mut new_threats = 0;
+        for window in square_windows_of(square) {
+            let empty = window & !board.board_mask();
+            if empty.count_ones() == 1 && (window & player_tiles).count_ones() == 3 {
+                new_threats |= empty;
+            }
+        }
+        *self.threats_mut(player) |= new_threats;
+    }
+
+    /// Updates the map after the tile of `player` on `square` is removed, given the board
+    /// afterwards
+    fn remove_tile(&mut self, square: u64, player: Player, board: &BitBoard) {
+        let opponent = match player {
+            Player::One => Player::Two,
+            Player::Two => Player::One,
+        };
+        // only threats sharing a window with the removed tile can be lost
+        let mut affected = square_windows_of(square).fold(0, |affected, window| affected | window)
+            & !board.board_mask();
+
+        let player_tiles = board.tiles(player);
+        let threats = self.threats_mut(player);
+        while affected != 0 {
+            // take the lowest square
+            let candidate = affected & affected.wrapping_neg();
+            affected ^= candidate;
+
+            *threats &= !candidate;
+            if is_threat(candidate, player_tiles) {
+                *threats |= candidate;
+            }
+        }
+        // the emptied square can now be a threat for the opponent
+        if is_threat(square, board.tiles(opponent)) {
+            *self.threats_mut(opponent) |= square;
+        }
+    }
+}
+
+/// A board that keeps its [`ThreatMap`] updated incrementally as moves are played and undone
+///
+/// [`ThreatMap`]: struct.ThreatMap.html
+#[derive(Clone)]
+pub struct ThreatBoard {
+    board: BitBoard,
+    threat_map: ThreatMap,
+    // move bitmaps of every move played with `play`
+    moves: Vec<u64>,
+}
+
+impl ThreatBoard {
+    /// Creates a new `ThreatBoard` from a bitboard
+    pub fn new(board: BitBoard) -> Self {
+        Self {
+            board,
+            threat_map: ThreatMap::new(&board),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Accesses the current threat map
+    pub fn threat_map(&self) -> ThreatMap {
+        self.threat_map
+    }
+
+    /// Returns the player to move
+    fn current_player(&self) -> Player {
+        // player one is to move after an even number of moves
+        match self.board.num_moves() % 2 {
+            1 => Player::Two,
+            _ => Player::One,
+        }
+    }
+
+    /// Plays a tile in a column, which must be playable
+    pub fn play(&mut self, column: usize) {
+        debug_assert!(self.board.playable(column));
+        let player = self.current_player();
+        let move_bitmap = self.board.possible_moves() & BitBoard::column_mask(column);
+
+        self.board.play(move_bitmap);
+        self.threat_map.add_tile(move_bitmap, player, &self.board);
+        self.moves.push(move_bitmap);
+    }
+
+    /// Undoes the last move played with [`play`], returning its column, or `None` if there are
+    /// no moves to undo
+    ///
+    /// [`play`]: #method.play
+    pub fn unplay(&mut self) -> Option<usize> {
+        let move_bitmap = self.moves.pop()?;
+
+        self.board.undo(move_bitmap);
+        self.threat_map
+            .remove_tile(move_bitmap, self.current_player(), &self.board);
+
+        Some(BitBoard::column_from_move(move_bitmap))
+    }
+}
+
+impl core::ops::Deref for ThreatBoard {
+    type Target = BitBoard;
+
+    fn deref(&self) -> &Self::Target {
+        &self.board
+    }
+}
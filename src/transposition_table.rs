@@ -1,16 +1,158 @@
 //! A transposition table to cache the results of Connect 4 game tree searches.
 
-use std::sync::{atomic::*, Arc};
-use std::{cell::RefCell, rc::Rc};
+use alloc::{rc::Rc, sync::Arc, vec, vec::Vec};
+use core::cell::RefCell;
+use core::sync::atomic::*;
+
+use crate::solver::{MAX_SCORE, MIN_SCORE};
+
+/// The largest stored value representing an upper bound, larger values are lower bounds
+const MAX_UPPER_BOUND_VALUE: u8 = (MAX_SCORE - MIN_SCORE + 1) as u8;
+
+/// A table caching the values of searched positions, which can be shared between [`Solver`]s by
+/// cloning it
+///
+/// # Notes
+/// Values are encoded as described by [`TableEntry`], where 0 is an empty entry. Tables may drop
+/// or overwrite entries at any time, but must only return a value stored for the same key.
+/// Implemented by [`TranspositionTable`] for use on a single thread and
+/// [`SharedTranspositionTable`] for use across threads
+///
+/// [`Solver`]: ../solver/struct.GenericSolver.html
+/// [`TableEntry`]: struct.TableEntry.html
+/// [`TranspositionTable`]: struct.TranspositionTable.html
+/// [`SharedTranspositionTable`]: struct.SharedTranspositionTable.html
+pub trait TranspositionStore: Clone {
+    /// Retrieve a value from the table, or 0 if the key isn't stored
+    fn get(&self, key: u64) -> u8;
+
+    /// Set a key-value pair in the table
+    fn set(&self, key: u64, value: u8);
+
+    /// Set a key-value pair for a position with `num_moves` tiles in the table
+    ///
+    /// Tables that don't prioritise entries by depth store the value with [`set`]
+    ///
+    /// [`set`]: #tymethod.set
+    fn set_at_depth(&self, key: u64, value: u8, num_moves: usize) {
+        let _ = num_moves;
+        self.set(key, value);
+    }
+
+    /// Returns this table as a [`SharedTranspositionTable`] if it is one, so parallel searches
+    /// can share it between their threads
+    ///
+    /// [`SharedTranspositionTable`]: struct.SharedTranspositionTable.html
+    fn as_shared(&self) -> Option<SharedTranspositionTable> {
+        None
+    }
+
+    /// Returns whether the table tells apart every key of up to `key_bits` bits, as required
+    /// for the keys of a board with `W * (H + 1)` bits
+    fn stores_keys_of(&self, key_bits: usize) -> bool {
+        let _ = key_bits;
+        true
+    }
+}
+
+/// The type of bound a transposition table entry places on the score of a position
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+    /// The score is at least the stored score
+    Lower,
+    /// The score is at most the stored score
+    Upper,
+}
+
+/// A decoded transposition table value
+///
+/// # Notes
+/// Values are stored in a single `u8`, where 0 is an empty entry. Upper bounds are stored as
+/// `score - MIN_SCORE + 1` and lower bounds as `score + MAX_SCORE - 2 * MIN_SCORE + 2`, so all
+/// lower bound values are larger than all upper bound values. Exact scores are stored as upper
+/// bounds
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TableEntry {
+    pub bound: Bound,
+    pub score: i32,
+}
+
+impl TableEntry {
+    /// Decodes a stored value, returning `None` for an empty entry
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => None,
+            1..=MAX_UPPER_BOUND_VALUE => Some(Self {
+                bound: Bound::Upper,
+                score: value as i32 + MIN_SCORE - 1,
+            }),
+            _ => Some(Self {
+                bound: Bound::Lower,
+                score: value as i32 + 2 * MIN_SCORE - MAX_SCORE - 2,
+            }),
+        }
+    }
+
+    /// Encodes the entry as a value to store in a table
+    pub fn value(&self) -> u8 {
+        match self.bound {
+            Bound::Upper => (self.score - MIN_SCORE + 1) as u8,
+            Bound::Lower => (self.score + MAX_SCORE - 2 * MIN_SCORE + 2) as u8,
+        }
+    }
+}
+
+/// Splits a key into the index of its slot in a table of `len` entries and the part of the key
+/// stored in the slot to identify it
+///
+/// Storing the quotient instead of the low bits of the key means a slot only needs to tell
+/// apart the keys that map to it. Only the low 48 bits of the quotient are kept, so keys are
+/// told apart exactly as long as the quotient fits (see [`stores_keys_of`])
+///
+/// [`stores_keys_of`]: fn.stores_keys_of.html
+fn split_key(key: u64, len: usize) -> (usize, u64) {
+    ((key % len as u64) as usize, key / len as u64)
+}
+
+/// The bits of the split key kept in a slot
+const STORED_KEY_MASK: u64 = (1 << 48) - 1;
+
+/// Returns whether every key of up to `key_bits` bits has a quotient that fits in
+/// [`STORED_KEY_MASK`] when split across `len` slots
+fn stores_keys_of(key_bits: usize, len: usize) -> bool {
+    ((1u128 << key_bits) - 1) / len as u128 <= STORED_KEY_MASK as u128
+}
 
 #[derive(Copy, Clone)]
 struct Entry {
     key: u32,
+    // high bits of the key, so keys of up to 48 bits are stored exactly
+    key_high: u16,
     value: u8,
+    // move counter of the position, entries stored with `set` are never protected
+    num_moves: u8,
 }
 impl Entry {
     pub fn new() -> Self {
-        Self { key: 0, value: 0 }
+        Self {
+            key: 0,
+            key_high: 0,
+            value: 0,
+            num_moves: u8::MAX,
+        }
+    }
+    fn with_key(key: u64) -> Self {
+        Self {
+            key: key as u32,
+            key_high: (key >> 32) as u16,
+            ..Self::new()
+        }
+    }
+    fn stored_key(&self) -> u64 {
+        (self.key_high as u64) << 32 | self.key as u64
+    }
+    fn matches(&self, key: u64) -> bool {
+        self.stored_key() == key & STORED_KEY_MASK
     }
 }
 
@@ -21,32 +163,143 @@ pub const TABLE_MAX_SIZE: usize = (1 << 23) + 9; // prime value minimises hash c
 #[derive(Clone)]
 struct TranspositionTableStorage {
     entries: Vec<Entry>,
+    // second tier of a two-tier table, parallel to `entries`, or empty
+    always_replace: Vec<Entry>,
+    replacement_threshold: usize,
 }
 
 impl TranspositionTableStorage {
-    pub fn new() -> Self {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "the table needs at least one entry");
+        Self {
+            entries: vec![Entry::new(); capacity],
+            always_replace: Vec::new(),
+            replacement_threshold: 0,
+        }
+    }
+    pub fn two_tier(capacity: usize) -> Self {
+        assert!(capacity > 1, "the table needs at least one entry per tier");
+        let tier_capacity = largest_prime_at_most(capacity / 2);
         Self {
-            entries: vec![Entry::new(); TABLE_MAX_SIZE],
+            entries: vec![Entry::new(); tier_capacity],
+            always_replace: vec![Entry::new(); tier_capacity],
+            replacement_threshold: 0,
         }
     }
+    fn is_two_tier(&self) -> bool {
+        !self.always_replace.is_empty()
+    }
     pub fn set(&mut self, key: u64, value: u8) {
-        // let key = key as u32;
-        // let entry = Entry { key, value };
-        let mut entry = Entry::new();
-        entry.key = key as u32;
+        let (index, key) = split_key(key, self.entries.len());
+        let mut entry = Entry::with_key(key);
         entry.value = value;
 
-        let len = self.entries.len();
-        self.entries[key as usize % len] = entry;
+        if self.is_two_tier() {
+            // update the position in place if it is already kept in the depth-preferred tier
+            if self.entries[index].matches(key) {
+                self.entries[index].value = value;
+            } else {
+                self.always_replace[index] = entry;
+            }
+            return;
+        }
+        self.entries[index] = entry;
+    }
+    pub fn set_at_depth(&mut self, key: u64, value: u8, num_moves: usize) {
+        let (index, key) = split_key(key, self.entries.len());
+        let entry = Entry {
+            value,
+            num_moves: num_moves.min(u8::MAX as usize) as u8,
+            ..Entry::with_key(key)
+        };
+        let two_tier = self.is_two_tier();
+        let existing = &mut self.entries[index];
+
+        if two_tier {
+            // the first tier keeps the position closest to the root, which is the most
+            // expensive to recalculate, and the second tier takes everything else
+            if existing.value == 0 || existing.matches(key) {
+                *existing = entry;
+            } else if entry.num_moves <= existing.num_moves {
+                // the replaced position moves down to the second tier
+                self.always_replace[index] = core::mem::replace(existing, entry);
+            } else {
+                self.always_replace[index] = entry;
+            }
+            return;
+        }
+
+        // keep entries close to the root, which are the most expensive to recalculate
+        let protected = (existing.num_moves as usize) < self.replacement_threshold
+            && (existing.num_moves as usize) < num_moves
+            && !existing.matches(key)
+            && existing.value != 0;
+        if !protected {
+            *existing = entry;
+        }
     }
     pub fn get(&self, key: u64) -> u8 {
-        let entry = self.entries[key as usize % self.entries.len()];
-        if entry.key == key as u32 {
+        let (index, key) = split_key(key, self.entries.len());
+        let entry = self.entries[index];
+        if entry.matches(key) {
             entry.value
+        } else if self.is_two_tier() && self.always_replace[index].matches(key) {
+            self.always_replace[index].value
         } else {
             0
         }
     }
+    pub fn merge(&mut self, other: &Self) {
+        assert!(
+            self.entries.len() == other.entries.len()
+                && self.always_replace.len() == other.always_replace.len(),
+            "only tables of the same capacity can be merged"
+        );
+        merge_entries(&mut self.entries, &other.entries);
+        merge_entries(&mut self.always_replace, &other.always_replace);
+    }
+    pub fn clear(&mut self) {
+        self.entries.fill(Entry::new());
+        self.always_replace.fill(Entry::new());
+    }
+    pub fn occupancy(&self) -> f64 {
+        let entries = || self.entries.iter().chain(self.always_replace.iter());
+        let occupied = entries().filter(|entry| entry.value != 0).count();
+        occupied as f64 / entries().count() as f64
+    }
+}
+
+/// Returns the largest prime no larger than `n`, or 1 if there is none
+fn largest_prime_at_most(n: usize) -> usize {
+    (2..=n).rev().find(|&n| is_prime(n)).unwrap_or(1)
+}
+
+fn is_prime(n: usize) -> bool {
+    (2..)
+        .take_while(|i| i * i <= n)
+        .all(|i| !n.is_multiple_of(i))
+}
+
+/// Copies the entries of `other` into `entries`, keeping the tightest bound of each position
+fn merge_entries(entries: &mut [Entry], other: &[Entry]) {
+    for (entry, other) in entries.iter_mut().zip(other.iter()) {
+        if entry.value == 0 {
+            *entry = *other;
+        } else if other.value != 0 && entry.stored_key() == other.stored_key() {
+            // values increase with the bound they represent, so keep the tightest bound.
+            // An upper and a lower bound can't be stored together, and either is valid
+            let upper_bounds = (
+                entry.value <= MAX_UPPER_BOUND_VALUE,
+                other.value <= MAX_UPPER_BOUND_VALUE,
+            );
+            match upper_bounds {
+                (true, true) => entry.value = entry.value.min(other.value),
+                (false, false) => entry.value = entry.value.max(other.value),
+                _ => {}
+            }
+        }
+        // on a collision keep the existing entry, either is valid
+    }
 }
 
 /// A shared, non-thread-safe transposition table
@@ -56,31 +309,124 @@ impl TranspositionTableStorage {
 /// This table uses `Rc<RefCell<...>>` internally to allow cheap cloning
 /// and sharing between [`Solver`] instances on a single thread
 ///
-/// **The table has a fixed capacity of ~42MB and key collisions will overwrite the previous
-/// value**
+/// **The table has a fixed capacity, by default ~42MB (see [`with_capacity`]), and key
+/// collisions will overwrite the previous value**
 ///
 /// See [`BitBoard`] for a description of the key values and [`Solver`] for a description of the values
 ///
-/// [`BitBoard`]: ../bitboard/struct.BitBoard.html#board-keys
-/// [`Solver`]: ../solver/struct.Solver.html#position-scoring
+/// [`BitBoard`]: ../bitboard/struct.GenericBitBoard.html#board-keys
+/// [`Solver`]: ../solver/struct.GenericSolver.html#position-scoring
+/// [`with_capacity`]: #method.with_capacity
 #[derive(Clone)]
 pub struct TranspositionTable(Rc<RefCell<TranspositionTableStorage>>);
 
 impl TranspositionTable {
-    /// Creates an empty transposition table
+    /// Creates an empty transposition table with the default capacity of [`TABLE_MAX_SIZE`]
+    /// entries
+    ///
+    /// [`TABLE_MAX_SIZE`]: constant.TABLE_MAX_SIZE.html
     pub fn new() -> Self {
-        Self(Rc::new(RefCell::new(TranspositionTableStorage::new())))
+        Self::with_capacity(TABLE_MAX_SIZE)
+    }
+
+    /// Creates an empty transposition table with room for `entries` entries
+    ///
+    /// # Notes
+    /// Each entry takes 8 bytes. Smaller tables give correct results, but overwrite more entries
+    /// and so search more nodes. Prime capacities minimise hash collisions. Boards with keys of
+    /// more than 48 bits need at least `2^(bits - 48)` entries in each tier, or a `Solver` using
+    /// the table panics
+    ///
+    /// # Panics
+    /// Panics if `entries` is 0
+    pub fn with_capacity(entries: usize) -> Self {
+        let storage = TranspositionTableStorage::new(entries);
+        Self(Rc::new(RefCell::new(storage)))
+    }
+
+    /// Creates an empty two-tier transposition table with room for `entries` entries in total
+    ///
+    /// # Notes
+    /// Each slot has two entries, so each tier holds half of `entries`, rounded down to a prime.
+    /// The first tier keeps the position with the fewest tiles stored in the slot with
+    /// [`set_at_depth`], which is the most expensive to recalculate, and the second tier takes
+    /// every other position, including one moved down from the first tier. Lookups check both
+    /// tiers. This uses the same memory as [`with_capacity`], and searches fewer nodes once the
+    /// table fills up
+    ///
+    /// # Panics
+    /// Panics if `entries` is less than 2
+    ///
+    /// [`set_at_depth`]: #method.set_at_depth
+    /// [`with_capacity`]: #method.with_capacity
+    pub fn with_two_tiers(entries: usize) -> Self {
+        let storage = TranspositionTableStorage::two_tier(entries);
+        Self(Rc::new(RefCell::new(storage)))
     }
 
-    /// Set a key-value pair in the transposition table
+    /// Creates an empty transposition table which protects the entries of positions with fewer
+    /// than `threshold` tiles from being replaced (see [`set_at_depth`])
+    ///
+    /// # Notes
+    /// The move counter of each entry is stored in what was padding, so this doesn't increase
+    /// the size of the table
+    ///
+    /// [`set_at_depth`]: #method.set_at_depth
+    pub fn with_replacement_threshold(threshold: usize) -> Self {
+        let table = Self::new();
+        table.0.borrow_mut().replacement_threshold = threshold;
+        table
+    }
+
+    /// Set a key-value pair in the transposition table, always replacing the previous entry
     pub fn set(&self, key: u64, value: u8) {
         self.0.borrow_mut().set(key, value);
     }
 
+    /// Set a key-value pair for a position with `num_moves` tiles in the transposition table
+    ///
+    /// # Notes
+    /// If the slot holds another position with fewer tiles than both `num_moves` and the
+    /// table's replacement threshold, the existing entry is kept instead. Positions closer to
+    /// the root of the search are much more expensive to recalculate
+    pub fn set_at_depth(&self, key: u64, value: u8, num_moves: usize) {
+        self.0.borrow_mut().set_at_depth(key, value, num_moves);
+    }
+
     /// Retrieve a value from the transposition table
     pub fn get(&self, key: u64) -> u8 {
         self.0.borrow().get(key)
     }
+
+    /// Copies the entries of another table into this one
+    ///
+    /// # Notes
+    /// Where both tables hold a bound for the same position, the tighter bound is kept. Where
+    /// the tables hold different positions in the same slot, this table's entry is kept.
+    /// Every entry in the merged table remains a valid bound for its position
+    ///
+    /// # Panics
+    /// Panics if the tables have different capacities
+    pub fn merge(&self, other: &TranspositionTable) {
+        if Rc::ptr_eq(&self.0, &other.0) {
+            return;
+        }
+        self.0.borrow_mut().merge(&other.0.borrow());
+    }
+
+    /// Empties every entry of the transposition table, including for every clone of it
+    ///
+    /// Entries from unrelated searches are still valid, but can push out entries that are
+    /// more useful to the next search
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    /// Returns the fraction of the table's entries that are in use, across both tiers of a
+    /// two-tier table
+    pub fn occupancy(&self) -> f64 {
+        self.0.borrow().occupancy()
+    }
 }
 
 impl Default for TranspositionTable {
@@ -89,51 +435,124 @@ impl Default for TranspositionTable {
     }
 }
 
-struct SharedEntry {
-    key: AtomicU32,
-    value: AtomicU8,
-}
-impl SharedEntry {
-    pub fn new() -> Self {
-        Self {
-            key: AtomicU32::new(0),
-            value: AtomicU8::new(0),
-        }
+impl TranspositionStore for TranspositionTable {
+    fn get(&self, key: u64) -> u8 {
+        TranspositionTable::get(self, key)
+    }
+
+    fn set(&self, key: u64, value: u8) {
+        TranspositionTable::set(self, key, value);
+    }
+
+    fn set_at_depth(&self, key: u64, value: u8, num_moves: usize) {
+        TranspositionTable::set_at_depth(self, key, value, num_moves);
     }
-    pub fn store(&self, key: u32, value: u8) {
-        self.key.store(key as u32, Ordering::Relaxed);
-        self.value.store(value as u8, Ordering::Relaxed);
+
+    fn stores_keys_of(&self, key_bits: usize) -> bool {
+        stores_keys_of(key_bits, self.0.borrow().entries.len())
     }
 }
 
-#[doc(hidden)]
+/// A shared, thread-safe, lock-free transposition table
+///
+/// # Notes
+///
+/// This table uses an `Arc` internally to allow cheap cloning and sharing between [`Solver`]
+/// instances on different threads. Each entry packs the stored part of its key and its value
+/// into a single `AtomicU64`, so concurrent reads and writes of a slot never see a mix of two
+/// entries: a lookup either returns a value stored for the same key or 0. Writes use relaxed
+/// ordering, so a thread may not see an entry stored by another thread yet, which only costs
+/// extra search. Entries are always replaced
+///
+/// See [`TranspositionTable`] for a description of the capacity, keys and values
+///
+/// [`Solver`]: ../solver/struct.GenericSolver.html
+/// [`TranspositionTable`]: struct.TranspositionTable.html
 #[derive(Clone)]
 pub struct SharedTranspositionTable {
-    entries: Arc<Vec<SharedEntry>>,
+    // stored key in the high 48 bits, value in the low 8 bits
+    entries: Arc<Vec<AtomicU64>>,
 }
+
 impl SharedTranspositionTable {
+    /// Creates an empty transposition table with the default capacity of [`TABLE_MAX_SIZE`]
+    /// entries
+    ///
+    /// [`TABLE_MAX_SIZE`]: constant.TABLE_MAX_SIZE.html
     pub fn new() -> Self {
-        let mut entries = Vec::with_capacity(TABLE_MAX_SIZE);
-        for _ in 0..TABLE_MAX_SIZE {
-            entries.push(SharedEntry::new());
-        }
+        Self::with_capacity(TABLE_MAX_SIZE)
+    }
+
+    /// Creates an empty transposition table with room for `entries` entries (see
+    /// [`TranspositionTable::with_capacity`])
+    ///
+    /// # Panics
+    /// Panics if `entries` is 0
+    ///
+    /// [`TranspositionTable::with_capacity`]: struct.TranspositionTable.html#method.with_capacity
+    pub fn with_capacity(entries: usize) -> Self {
+        assert!(entries > 0, "the table needs at least one entry");
         Self {
-            entries: Arc::new(entries),
+            entries: Arc::new((0..entries).map(|_| AtomicU64::new(0)).collect()),
         }
     }
+
+    /// Set a key-value pair in the transposition table, replacing the previous entry
     pub fn set(&self, key: u64, value: u8) {
-        let i = key as usize % self.entries.len();
-        self.entries[i].store(key as u32 ^ value as u32, value);
+        let (index, key) = split_key(key, self.entries.len());
+        let entry = (key & STORED_KEY_MASK) << 8 | value as u64;
+        self.entries[index].store(entry, Ordering::Relaxed);
     }
+
+    /// Retrieve a value from the transposition table
     pub fn get(&self, key: u64) -> u8 {
-        let entry = &self.entries[key as usize % self.entries.len()];
-        let data = entry.value.load(Ordering::Relaxed);
-        if entry.key.load(Ordering::Relaxed) == key as u32 ^ data as u32 {
-            data
+        let (index, key) = split_key(key, self.entries.len());
+        let entry = self.entries[index].load(Ordering::Relaxed);
+        if entry >> 8 == key & STORED_KEY_MASK {
+            entry as u8
         } else {
             0
         }
     }
+
+    /// Empties every entry of the transposition table, including for every clone of it
+    ///
+    /// # Notes
+    /// Each entry is zeroed atomically, but entries stored by other threads while the table is
+    /// being cleared may be kept
+    pub fn clear(&self) {
+        for entry in self.entries.iter() {
+            entry.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the fraction of the table's entries that are in use
+    pub fn occupancy(&self) -> f64 {
+        let occupied = self
+            .entries
+            .iter()
+            .filter(|entry| entry.load(Ordering::Relaxed) as u8 != 0)
+            .count();
+        occupied as f64 / self.entries.len() as f64
+    }
+}
+
+impl TranspositionStore for SharedTranspositionTable {
+    fn get(&self, key: u64) -> u8 {
+        SharedTranspositionTable::get(self, key)
+    }
+
+    fn set(&self, key: u64, value: u8) {
+        SharedTranspositionTable::set(self, key, value);
+    }
+
+    fn as_shared(&self) -> Option<SharedTranspositionTable> {
+        Some(self.clone())
+    }
+
+    fn stores_keys_of(&self, key_bits: usize) -> bool {
+        stores_keys_of(key_bits, self.entries.len())
+    }
 }
 
 impl Default for SharedTranspositionTable {